@@ -0,0 +1,173 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, DeriveInput, Error, GenericParam, Lit, Meta, NestedMeta,
+};
+
+/// Implements `NetworkTypeUuid`, either from `#[uuid = "..."]` or by hashing the type's path.
+/// The uuids of type parameters are mixed in, so they have to implement it too.
+#[proc_macro_derive(NetworkTypeUuid, attributes(uuid))]
+pub fn derive_network_type_uuid(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let mut uuid = match parse_uuid(&input) {
+        Ok(Some(uuid)) => quote!(#uuid),
+        Ok(None) => {
+            let name = input.ident.to_string();
+            quote!(network::type_path_hash(
+                concat!(module_path!(), "::", #name)
+            ))
+        }
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let mut params = Vec::new();
+
+    for param in &input.generics.params {
+        match param {
+            GenericParam::Type(param) => params.push(param.ident.clone()),
+            GenericParam::Const(param) => {
+                return Error::new_spanned(param, "const parameters can't be told apart by uuid")
+                    .to_compile_error()
+                    .into();
+            }
+            GenericParam::Lifetime(_) => (),
+        }
+    }
+
+    for param in &params {
+        uuid = quote!(network::type_param_hash(
+            #uuid,
+            &<#param as network::NetworkTypeUuid>::UUID
+        ));
+
+        input
+            .generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#param: network::NetworkTypeUuid));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    TokenStream::from(quote! {
+        impl #impl_generics network::NetworkTypeUuid for #ident #ty_generics #where_clause {
+            const UUID: network::__private::Uuid = network::__private::Uuid::from_u128(#uuid);
+        }
+    })
+}
+
+/// Implements `SyncableComponent`, encoding with serde unless `#[sync(reflect)]` is given.
+#[proc_macro_derive(SyncableComponent, attributes(sync))]
+pub fn derive_syncable_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let reflect = match parse_encoding(&input) {
+        Ok(reflect) => reflect,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = if reflect {
+        quote! {
+            fn from_bytes(
+                bytes: &[u8],
+                type_registry: &network::__private::TypeRegistry,
+                _wire_format: network::WireFormat,
+            ) -> std::result::Result<Self, network::Error> {
+                network::reflect_from_bytes(bytes, type_registry)
+            }
+
+            fn to_bytes(
+                &self,
+                type_registry: &network::__private::TypeRegistry,
+                _wire_format: network::WireFormat,
+            ) -> Vec<u8> {
+                network::reflect_to_bytes(self, type_registry)
+            }
+        }
+    } else {
+        quote! {
+            fn from_bytes(
+                bytes: &[u8],
+                _type_registry: &network::__private::TypeRegistry,
+                wire_format: network::WireFormat,
+            ) -> std::result::Result<Self, network::Error> {
+                wire_format.decode(bytes)
+            }
+
+            fn to_bytes(
+                &self,
+                _type_registry: &network::__private::TypeRegistry,
+                wire_format: network::WireFormat,
+            ) -> Vec<u8> {
+                wire_format.encode(self).unwrap()
+            }
+        }
+    };
+
+    TokenStream::from(quote! {
+        impl #impl_generics network::SyncableComponent for #ident #ty_generics #where_clause {
+            #body
+        }
+    })
+}
+
+fn parse_uuid(input: &DeriveInput) -> Result<Option<u128>, Error> {
+    for attr in &input.attrs {
+        if !attr.path.is_ident("uuid") {
+            continue;
+        }
+
+        if let Meta::NameValue(name_value) = attr.parse_meta()? {
+            if let Lit::Str(lit) = &name_value.lit {
+                let hex: String = lit.value().chars().filter(|c| *c != '-').collect();
+
+                if hex.len() == 32 {
+                    if let Ok(uuid) = u128::from_str_radix(&hex, 16) {
+                        return Ok(Some(uuid));
+                    }
+                }
+
+                return Err(Error::new_spanned(lit, "invalid uuid"));
+            }
+        }
+
+        return Err(Error::new_spanned(
+            attr,
+            "expected #[uuid = \"xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx\"]",
+        ));
+    }
+
+    Ok(None)
+}
+
+fn parse_encoding(input: &DeriveInput) -> Result<bool, Error> {
+    for attr in &input.attrs {
+        if !attr.path.is_ident("sync") {
+            continue;
+        }
+
+        if let Meta::List(list) = attr.parse_meta()? {
+            if let Some(NestedMeta::Meta(Meta::Path(path))) = list.nested.first() {
+                if path.is_ident("serde") {
+                    return Ok(false);
+                } else if path.is_ident("reflect") {
+                    return Ok(true);
+                }
+            }
+        }
+
+        return Err(Error::new_spanned(
+            attr,
+            "expected #[sync(serde)] or #[sync(reflect)]",
+        ));
+    }
+
+    Ok(false)
+}
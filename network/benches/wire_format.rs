@@ -0,0 +1,139 @@
+use bevy::{prelude::*, reflect::TypeRegistry};
+use criterion::{criterion_group, criterion_main, Criterion};
+use network::*;
+use std::collections::HashMap;
+
+/// Same shape as the game's `Animator`.
+#[derive(Serialize, Deserialize, NetworkTypeUuid, SyncableComponent)]
+struct Animator {
+    animations: HashMap<String, Animation>,
+    current_animation: String,
+    current_frame_time: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Animation {
+    textures: Vec<u32>,
+    frame_rate: f32,
+}
+
+fn animator() -> Animator {
+    let names = [
+        "walk_up_left",
+        "walk_left",
+        "walk_down_left",
+        "walk_down",
+        "walk_down_right",
+        "walk_right",
+        "walk_up_right",
+        "walk_up",
+    ];
+
+    Animator {
+        animations: names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let animation = Animation {
+                    textures: (i as u32 * 24..(i as u32 + 1) * 24).collect(),
+                    frame_rate: 1.0 / 12.0,
+                };
+
+                (name.to_string(), animation)
+            })
+            .collect(),
+        current_animation: "walk_down".to_string(),
+        current_frame_time: 0.25,
+    }
+}
+
+/// A tick's worth of updates for 100 entities.
+fn payloads<T: SyncableComponent>(
+    component: &T,
+    type_registry: &TypeRegistry,
+    wire_format: WireFormat,
+) -> Vec<Payload> {
+    (0..100)
+        .map(|i| Payload::ComponentUpdate {
+            target_entity: NetworkEntity(i),
+            network_type_uuid: T::UUID,
+            data: component.to_bytes(type_registry, wire_format),
+        })
+        .collect()
+}
+
+fn bench_component<T: SyncableComponent>(
+    c: &mut Criterion,
+    name: &str,
+    component: &T,
+    type_registry: &TypeRegistry,
+) {
+    let mut group = c.benchmark_group(name);
+
+    for wire_format in [WireFormat::Cbor, WireFormat::Bincode].iter().cloned() {
+        let payloads = payloads(component, type_registry, wire_format);
+        let bytes = wire_format.encode(&payloads).unwrap();
+
+        println!(
+            "{} {:?}: {} bytes per component, {} bytes per 100 updates",
+            name,
+            wire_format,
+            component.to_bytes(type_registry, wire_format).len(),
+            bytes.len()
+        );
+
+        group.bench_function(format!("{:?}/encode", wire_format), |b| {
+            b.iter(|| {
+                let payloads = payloads(component, type_registry, wire_format);
+                wire_format.encode(&payloads).unwrap()
+            })
+        });
+
+        group.bench_function(format!("{:?}/decode", wire_format), |b| {
+            b.iter(|| {
+                let payloads: Vec<Payload> = wire_format.decode(&bytes).unwrap();
+
+                for payload in &payloads {
+                    if let Payload::ComponentUpdate { data, .. } = payload {
+                        T::from_bytes(data, type_registry, wire_format).unwrap();
+                    }
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn wire_format(c: &mut Criterion) {
+    let type_registry = TypeRegistry::default();
+    type_registry.write().register::<Transform>();
+    type_registry.write().register::<GlobalTransform>();
+
+    let transform = Transform::from_translation(Vec3::new(12.5, -3.0, 0.5));
+
+    bench_component(c, "transform", &transform, &type_registry);
+    bench_component(
+        c,
+        "global_transform",
+        &GlobalTransform::from(transform),
+        &type_registry,
+    );
+    bench_component(c, "animator", &animator(), &type_registry);
+
+    let settings = CompactTransformSettings::default();
+    let bytes = settings.encode(&transform, Some(transform.scale));
+    println!("compact_transform: {} bytes per component", bytes.len());
+
+    let mut group = c.benchmark_group("compact_transform");
+    group.bench_function("encode", |b| {
+        b.iter(|| settings.encode(&transform, Some(transform.scale)))
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| settings.decode(&bytes, &transform).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, wire_format);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+#![no_main]
+use bevy::{prelude::*, reflect::TypeRegistry};
+use libfuzzer_sys::fuzz_target;
+use network::*;
+
+fuzz_target!(|bytes: &[u8]| {
+    let type_registry = TypeRegistry::default();
+    type_registry.write().register::<Vec3>();
+    type_registry.write().register::<Quat>();
+    type_registry.write().register::<Transform>();
+    type_registry.write().register::<GlobalTransform>();
+
+    for wire_format in [WireFormat::Cbor, WireFormat::Bincode].iter() {
+        let _ = Transform::from_bytes(bytes, &type_registry, *wire_format);
+        let _ = GlobalTransform::from_bytes(bytes, &type_registry, *wire_format);
+    }
+
+    let _ = CompactTransformSettings::default().decode(bytes, &Transform::default());
+});
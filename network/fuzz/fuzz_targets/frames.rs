@@ -0,0 +1,25 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use network::*;
+
+// The first byte picks how the stream is split up as it arrives.
+fuzz_target!(|bytes: &[u8]| {
+    let (chunk_len, stream) = match bytes.split_first() {
+        Some((chunk_len, stream)) => (*chunk_len as usize + 1, stream),
+        None => return,
+    };
+
+    let mut decoder = FrameDecoder::new();
+
+    for chunk in stream.chunks(chunk_len) {
+        decoder.extend(chunk);
+
+        loop {
+            match decoder.next_frame() {
+                Ok(Some(frame)) => assert!(frame.len() <= MAX_FRAME_LEN),
+                Ok(None) => break,
+                Err(_) => return,
+            }
+        }
+    }
+});
@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use network::*;
+
+fuzz_target!(|bytes: &[u8]| {
+    let _ = serde_cbor::from_slice::<Handshake>(bytes);
+});
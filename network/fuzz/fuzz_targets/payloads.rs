@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use network::*;
+
+// What `ConnectionInner::receive` does with every frame once it's decrypted, with a full default
+// `InboundBudget`.
+fuzz_target!(|frame: &[u8]| {
+    let max_len = frame.len() + InboundBudget::default().bytes_per_second as usize;
+
+    if let Ok(bytes) = decompress_frame_limited(frame, max_len) {
+        let _ = WireFormat::Cbor.decode::<Vec<Payload>>(&bytes);
+        let _ = WireFormat::Bincode.decode::<Vec<Payload>>(&bytes);
+    }
+});
@@ -0,0 +1,231 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    net::{IpAddr, TcpStream},
+    time::{Duration, Instant},
+};
+
+/// Why the listener refused a connection, sent to the client before closing.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Rejection {
+    Banned,
+    NotAllowed,
+    RateLimited,
+    ServerFull,
+}
+
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    /// Connections a single address can open within `window`.
+    pub connections: usize,
+    pub window: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Allowlist {
+    pub addrs: HashSet<IpAddr>,
+    pub players: HashSet<PlayerId>,
+}
+
+/// Decides who [`listening_system`] accepts.
+#[derive(Default)]
+pub struct AccessControl {
+    pub banned_addrs: HashSet<IpAddr>,
+    pub banned_players: HashSet<PlayerId>,
+    /// Only these addresses and players can connect when set.
+    pub allowlist: Option<Allowlist>,
+    pub rate_limit: Option<RateLimit>,
+    pub max_players: Option<usize>,
+    recent_connections: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+impl AccessControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ban_addr(&mut self, addr: IpAddr) {
+        self.banned_addrs.insert(addr);
+    }
+
+    pub fn ban_player(&mut self, player_id: PlayerId) {
+        self.banned_players.insert(player_id);
+    }
+
+    /// Checked before handshaking, counts the connection towards the rate limit.
+    pub fn check_addr(&mut self, addr: IpAddr, players: usize) -> Result<(), Rejection> {
+        if self.banned_addrs.contains(&addr) {
+            return Err(Rejection::Banned);
+        }
+
+        if let Some(rate_limit) = &self.rate_limit {
+            let now = Instant::now();
+            let recent = self
+                .recent_connections
+                .entry(addr)
+                .or_insert(VecDeque::new());
+
+            while let Some(connected_at) = recent.front() {
+                if now.duration_since(*connected_at) < rate_limit.window {
+                    break;
+                }
+
+                recent.pop_front();
+            }
+
+            if recent.len() >= rate_limit.connections {
+                return Err(Rejection::RateLimited);
+            }
+
+            recent.push_back(now);
+        }
+
+        if let Some(max_players) = self.max_players {
+            if players >= max_players {
+                return Err(Rejection::ServerFull);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checked once the connection has logged in, if it did.
+    pub fn check_player(&self, addr: IpAddr, player_id: Option<PlayerId>) -> Result<(), Rejection> {
+        if let Some(player_id) = player_id {
+            if self.banned_players.contains(&player_id) {
+                return Err(Rejection::Banned);
+            }
+        }
+
+        if let Some(allowlist) = &self.allowlist {
+            let allowed = allowlist.addrs.contains(&addr)
+                || player_id
+                    .map(|player_id| allowlist.players.contains(&player_id))
+                    .unwrap_or(false);
+
+            if !allowed {
+                return Err(Rejection::NotAllowed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets rate limit history older than the window.
+    pub fn prune(&mut self) {
+        if let Some(rate_limit) = &self.rate_limit {
+            let window = rate_limit.window;
+
+            self.recent_connections.retain(|_, recent| {
+                recent
+                    .back()
+                    .map(|connected_at| connected_at.elapsed() < window)
+                    .unwrap_or(false)
+            });
+        }
+    }
+}
+
+/// Answers the client's handshake with a goodbye instead of handshaking.
+pub fn reject_stream(
+    stream: TcpStream,
+    reason: DisconnectReason,
+    network_settings: &NetworkSettings,
+) -> Result<(), crate::Error> {
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+
+    let mut handshake = Handshake::new(HandshakeActorIds::None, network_settings);
+    handshake.goodbye = Some(reason);
+
+    let mut stream = FramedStream::new(stream);
+    stream.write_frame(&serde_cbor::to_vec(&handshake)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::Ipv4Addr, thread};
+
+    fn addr(last: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, last))
+    }
+
+    #[test]
+    fn bans_are_refused() {
+        let mut access_control = AccessControl::new();
+        access_control.ban_addr(addr(1));
+        access_control.ban_player(PlayerId(7));
+
+        assert_eq!(
+            access_control.check_addr(addr(1), 0),
+            Err(Rejection::Banned)
+        );
+        assert_eq!(access_control.check_addr(addr(2), 0), Ok(()));
+        assert_eq!(
+            access_control.check_player(addr(2), Some(PlayerId(7))),
+            Err(Rejection::Banned)
+        );
+        assert_eq!(
+            access_control.check_player(addr(2), Some(PlayerId(8))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn allowlists_take_addresses_or_players() {
+        let mut access_control = AccessControl::new();
+        let mut allowlist = Allowlist::default();
+        allowlist.addrs.insert(addr(1));
+        allowlist.players.insert(PlayerId(7));
+        access_control.allowlist = Some(allowlist);
+
+        assert_eq!(access_control.check_player(addr(1), None), Ok(()));
+        assert_eq!(
+            access_control.check_player(addr(2), Some(PlayerId(7))),
+            Ok(())
+        );
+        assert_eq!(
+            access_control.check_player(addr(2), Some(PlayerId(8))),
+            Err(Rejection::NotAllowed)
+        );
+        assert_eq!(
+            access_control.check_player(addr(2), None),
+            Err(Rejection::NotAllowed)
+        );
+    }
+
+    #[test]
+    fn rate_limits_are_per_address_and_expire() {
+        let mut access_control = AccessControl::new();
+        access_control.rate_limit = Some(RateLimit {
+            connections: 2,
+            window: Duration::from_millis(100),
+        });
+
+        assert_eq!(access_control.check_addr(addr(1), 0), Ok(()));
+        assert_eq!(access_control.check_addr(addr(1), 0), Ok(()));
+        assert_eq!(
+            access_control.check_addr(addr(1), 0),
+            Err(Rejection::RateLimited)
+        );
+        assert_eq!(access_control.check_addr(addr(2), 0), Ok(()));
+
+        thread::sleep(Duration::from_millis(150));
+
+        assert_eq!(access_control.check_addr(addr(1), 0), Ok(()));
+    }
+
+    #[test]
+    fn full_servers_are_refused() {
+        let mut access_control = AccessControl::new();
+        access_control.max_players = Some(2);
+
+        assert_eq!(access_control.check_addr(addr(1), 1), Ok(()));
+        assert_eq!(
+            access_control.check_addr(addr(1), 2),
+            Err(Rejection::ServerFull)
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use hmac::Hmac;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+const HASH_ROUNDS: u32 = 10_000;
+
+/// Identifies a player across sessions, unlike [`ActorId`](crate::ActorId) which is only valid
+/// for a single connection.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct PlayerId(pub u64);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl Credentials {
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LoginRejection {
+    InvalidCredentials,
+    LoginRequired,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LoginResponse {
+    Accepted { player_id: PlayerId },
+    Rejected(LoginRejection),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Account {
+    player_id: PlayerId,
+    salt: [u8; 16],
+    password_hash: [u8; 32],
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct AccountFile {
+    accounts: HashMap<String, Account>,
+    next_player_id: u64,
+}
+
+/// Clones share their accounts, so logins can be checked on the threads handshakes run on.
+#[derive(Clone)]
+pub struct AccountStore {
+    path: Option<PathBuf>,
+    file: Arc<Mutex<AccountFile>>,
+
+    /// Creates an account the first time an unknown username logs in, off by default since
+    /// whoever logs in first with a name gets it.
+    pub register_unknown: bool,
+}
+
+impl Default for AccountStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccountStore {
+    /// Creates a store that only lives in memory.
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            file: Arc::new(Mutex::new(AccountFile::default())),
+            register_unknown: false,
+        }
+    }
+
+    /// Loads the accounts at `path` if it exists, changes are written back to it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+        let path = path.as_ref().to_path_buf();
+
+        let file = if path.exists() {
+            serde_cbor::from_slice(&fs::read(&path)?)?
+        } else {
+            AccountFile::default()
+        };
+
+        Ok(Self {
+            path: Some(path),
+            file: Arc::new(Mutex::new(file)),
+            register_unknown: false,
+        })
+    }
+
+    pub fn register(&mut self, credentials: &Credentials) -> Result<PlayerId, crate::Error> {
+        if self.get_player_id(&credentials.username).is_some() {
+            return Err(crate::Error::AccountExists);
+        }
+
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+
+        // hashing takes a while, other logins shouldn't wait for it
+        let password_hash = hash_password(&credentials.password, &salt);
+
+        let mut file = self.file.lock().unwrap();
+
+        if file.accounts.contains_key(&credentials.username) {
+            return Err(crate::Error::AccountExists);
+        }
+
+        let player_id = PlayerId(file.next_player_id);
+        file.next_player_id += 1;
+
+        let account = Account {
+            player_id,
+            salt,
+            password_hash,
+        };
+
+        file.accounts.insert(credentials.username.clone(), account);
+        self.save(&file)?;
+
+        Ok(player_id)
+    }
+
+    pub fn login(&mut self, credentials: &Credentials) -> Result<PlayerId, LoginRejection> {
+        let account = self
+            .file
+            .lock()
+            .unwrap()
+            .accounts
+            .get(&credentials.username)
+            .cloned();
+
+        match account {
+            Some(account) => {
+                let password_hash = hash_password(&credentials.password, &account.salt);
+
+                if constant_time_eq(&password_hash, &account.password_hash) {
+                    Ok(account.player_id)
+                } else {
+                    Err(LoginRejection::InvalidCredentials)
+                }
+            }
+            None if self.register_unknown => self
+                .register(credentials)
+                .map_err(|_| LoginRejection::InvalidCredentials),
+            None => Err(LoginRejection::InvalidCredentials),
+        }
+    }
+
+    pub fn get_player_id(&self, username: &str) -> Option<PlayerId> {
+        self.file
+            .lock()
+            .unwrap()
+            .accounts
+            .get(username)
+            .map(|account| account.player_id)
+    }
+
+    pub fn get_username(&self, player_id: PlayerId) -> Option<String> {
+        self.file
+            .lock()
+            .unwrap()
+            .accounts
+            .iter()
+            .find(|(_, account)| account.player_id == player_id)
+            .map(|(username, _)| username.clone())
+    }
+
+    fn save(&self, file: &AccountFile) -> Result<(), crate::Error> {
+        if let Some(path) = &self.path {
+            let tmp_path = path.with_extension("tmp");
+
+            fs::write(&tmp_path, serde_cbor::to_vec(file)?)?;
+            fs::rename(&tmp_path, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn hash_password(password: &str, salt: &[u8; 16]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, HASH_ROUNDS, &mut hash);
+    hash
+}
+
+/// Only the length leaks, not where the first difference is.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_usernames_register_on_login() {
+        let mut account_store = AccountStore::new();
+        account_store.register_unknown = true;
+
+        let player_id = account_store
+            .login(&Credentials::new("alice", "hunter2"))
+            .unwrap();
+
+        assert_eq!(account_store.get_player_id("alice"), Some(player_id));
+        assert_eq!(
+            account_store.get_username(player_id),
+            Some("alice".to_string())
+        );
+        assert_eq!(
+            account_store.login(&Credentials::new("alice", "hunter2")),
+            Ok(player_id)
+        );
+    }
+
+    #[test]
+    fn wrong_passwords_and_unknown_usernames_are_rejected() {
+        let mut account_store = AccountStore::new();
+        account_store
+            .register(&Credentials::new("alice", "hunter2"))
+            .unwrap();
+
+        assert_eq!(
+            account_store.login(&Credentials::new("alice", "hunter3")),
+            Err(LoginRejection::InvalidCredentials)
+        );
+        assert_eq!(
+            account_store.login(&Credentials::new("bob", "hunter2")),
+            Err(LoginRejection::InvalidCredentials)
+        );
+        assert!(matches!(
+            account_store.register(&Credentials::new("alice", "other")),
+            Err(crate::Error::AccountExists)
+        ));
+    }
+
+    #[test]
+    fn clones_share_their_accounts() {
+        let mut account_store = AccountStore::new();
+        let mut clone = account_store.clone();
+
+        let player_id = clone
+            .register(&Credentials::new("alice", "hunter2"))
+            .unwrap();
+
+        assert_eq!(account_store.get_player_id("alice"), Some(player_id));
+        assert_eq!(
+            account_store.login(&Credentials::new("alice", "hunter2")),
+            Ok(player_id)
+        );
+    }
+
+    #[test]
+    fn accounts_persist_across_opens() {
+        let path = std::env::temp_dir().join(format!("accounts-{}.cbor", std::process::id()));
+
+        let player_id = AccountStore::open(&path)
+            .unwrap()
+            .register(&Credentials::new("alice", "hunter2"))
+            .unwrap();
+
+        let mut account_store = AccountStore::open(&path).unwrap();
+        let login = account_store.login(&Credentials::new("alice", "hunter2"));
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(login, Ok(player_id));
+    }
+
+    #[test]
+    fn constant_time_eq_compares_whole_slices() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+    }
+}
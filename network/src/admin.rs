@@ -0,0 +1,372 @@
+use crate::*;
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    io::BufRead,
+    str::FromStr,
+    sync::{mpsc, Mutex},
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AdminPayload {
+    /// A console line from an admin connection.
+    Command(String),
+    Response(String),
+    Broadcast(String),
+}
+
+/// Where an admin command came from, its responses go back there.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AdminSource {
+    Console,
+    Actor(ActorId),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdminCommandKind {
+    Help,
+    List,
+    Kick(ActorId),
+    Ban(ActorId),
+    Teleport { actor_id: ActorId, position: Vec2 },
+    Spawn { name: String, args: Vec<String> },
+    Broadcast(String),
+    Shutdown,
+}
+
+const HELP: &str = "commands: list, kick <actor>, ban <actor>, teleport <actor> <x> <y>, \
+                    spawn <name> [args..], broadcast <message>, shutdown";
+
+impl FromStr for AdminCommandKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+
+        let actor_id = |i: usize| -> Result<ActorId, String> {
+            words
+                .get(i)
+                .and_then(|word| word.parse().ok())
+                .map(ActorId)
+                .ok_or_else(|| format!("expected an actor id, {}", HELP))
+        };
+
+        let number = |i: usize| -> Result<f32, String> {
+            words
+                .get(i)
+                .and_then(|word| word.parse().ok())
+                .ok_or_else(|| format!("expected a number, {}", HELP))
+        };
+
+        match words.first() {
+            Some(&"help") => Ok(AdminCommandKind::Help),
+            Some(&"list") => Ok(AdminCommandKind::List),
+            Some(&"kick") => Ok(AdminCommandKind::Kick(actor_id(1)?)),
+            Some(&"ban") => Ok(AdminCommandKind::Ban(actor_id(1)?)),
+            Some(&"teleport") => Ok(AdminCommandKind::Teleport {
+                actor_id: actor_id(1)?,
+                position: Vec2::new(number(2)?, number(3)?),
+            }),
+            Some(&"spawn") if words.len() > 1 => Ok(AdminCommandKind::Spawn {
+                name: words[1].to_string(),
+                args: words[2..].iter().map(|word| word.to_string()).collect(),
+            }),
+            Some(&"broadcast") if words.len() > 1 => {
+                Ok(AdminCommandKind::Broadcast(words[1..].join(" ")))
+            }
+            Some(&"shutdown") => Ok(AdminCommandKind::Shutdown),
+            _ => Err(format!("unknown command, {}", HELP)),
+        }
+    }
+}
+
+/// Sent for every authorized admin command, teleport and spawn are left for the game to handle.
+#[derive(Clone, Debug)]
+pub struct AdminCommand {
+    pub source: AdminSource,
+    pub kind: AdminCommandKind,
+}
+
+/// Printed on the console or sent back to the admin connection.
+#[derive(Clone, Debug)]
+pub struct AdminResponse {
+    pub source: AdminSource,
+    pub text: String,
+}
+
+impl AdminResponse {
+    pub fn new(source: AdminSource, text: impl Into<String>) -> Self {
+        Self {
+            source,
+            text: text.into(),
+        }
+    }
+}
+
+/// Lines typed into stdin, read on a separate thread.
+pub struct AdminConsole {
+    lines: Mutex<mpsc::Receiver<String>>,
+}
+
+impl AdminConsole {
+    pub fn stdin() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => return,
+                };
+
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            lines: Mutex::new(receiver),
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().try_iter().collect()
+    }
+}
+
+/// Usernames allowed to send commands over an admin connection.
+#[derive(Clone, Default)]
+pub struct AdminSettings {
+    pub admins: HashSet<String>,
+}
+
+/// Server side admin commands from stdin and from logged in admins, see [`AdminCommand`].
+pub struct AdminPlugin {
+    pub console: bool,
+    pub settings: AdminSettings,
+}
+
+impl Default for AdminPlugin {
+    fn default() -> Self {
+        Self {
+            console: true,
+            settings: AdminSettings::default(),
+        }
+    }
+}
+
+impl AdminPlugin {
+    pub fn with_admin(mut self, username: impl Into<String>) -> Self {
+        self.settings.admins.insert(username.into());
+        self
+    }
+
+    pub fn without_console(mut self) -> Self {
+        self.console = false;
+        self
+    }
+}
+
+impl Plugin for AdminPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        if self.console {
+            app_builder.add_resource(AdminConsole::stdin());
+            app_builder.add_system(admin_console_system);
+        }
+
+        if !app_builder.resources().contains::<AccountStore>() {
+            app_builder.init_resource::<AccountStore>();
+        }
+
+        if !app_builder.resources().contains::<AccessControl>() {
+            app_builder.init_resource::<AccessControl>();
+        }
+
+        app_builder.add_resource(self.settings.clone());
+
+        app_builder.add_event::<AdminCommand>();
+        app_builder.add_event::<AdminResponse>();
+
+        app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, admin_receiving_system);
+        app_builder.add_system(admin_system);
+        app_builder.add_system_to_stage(bevy::app::stage::POST_UPDATE, admin_response_system);
+    }
+}
+
+pub fn admin_console_system(
+    console: Res<AdminConsole>,
+    mut admin_commands: ResMut<Events<AdminCommand>>,
+    mut admin_responses: ResMut<Events<AdminResponse>>,
+) {
+    for line in console.lines() {
+        match line.parse() {
+            Ok(kind) => admin_commands.send(AdminCommand {
+                source: AdminSource::Console,
+                kind,
+            }),
+            Err(e) => admin_responses.send(AdminResponse::new(AdminSource::Console, e)),
+        }
+    }
+}
+
+pub fn admin_receiving_system(
+    admin_settings: Res<AdminSettings>,
+    account_store: Res<AccountStore>,
+    mut event_reader: Local<EventReader<Message>>,
+    messages: Res<Events<Message>>,
+    mut admin_commands: ResMut<Events<AdminCommand>>,
+    mut admin_responses: ResMut<Events<AdminResponse>>,
+) {
+    for message in event_reader.iter(&messages) {
+        if let Payload::Admin(AdminPayload::Command(line)) = &message.payload {
+            let source = AdminSource::Actor(message.sender.id());
+
+            let authorized = message
+                .sender
+                .player_id()
+                .and_then(|player_id| account_store.get_username(player_id))
+                .map(|username| admin_settings.admins.contains(&username))
+                .unwrap_or(false);
+
+            if !authorized {
+                warn!("{:?} sent an admin command without being an admin", source);
+                admin_responses.send(AdminResponse::new(source, "not authorized"));
+                continue;
+            }
+
+            info!("{:?}: {}", source, line);
+
+            match line.parse() {
+                Ok(kind) => admin_commands.send(AdminCommand { source, kind }),
+                Err(e) => admin_responses.send(AdminResponse::new(source, e)),
+            }
+        }
+    }
+}
+
+/// Handles the commands that only need the network.
+pub fn admin_system(
+    mut event_reader: Local<EventReader<AdminCommand>>,
+    admin_commands: Res<Events<AdminCommand>>,
+    mut admin_responses: ResMut<Events<AdminResponse>>,
+    mut connection_events: ResMut<Events<ConnectionEvent>>,
+    mut network_handle: ResMut<NetworkHandle>,
+    mut access_control: ResMut<AccessControl>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+) {
+    for command in event_reader.iter(&admin_commands) {
+        let response = match &command.kind {
+            AdminCommandKind::Help => HELP.to_string(),
+            AdminCommandKind::List => {
+                let mut lines = Vec::new();
+
+                for (connection_id, connection) in connection_manager.connections() {
+                    let actor = connection.actor();
+
+                    lines.push(format!(
+                        "{:?} {:?} player: {:?} addr: {:?} rtt: {:?}",
+                        connection_id,
+                        actor.id(),
+                        actor.player_id(),
+                        connection.addr(),
+                        connection.statistics().round_trip_time,
+                    ));
+                }
+
+                lines.join("\n")
+            }
+            AdminCommandKind::Kick(actor_id) | AdminCommandKind::Ban(actor_id) => {
+                let internal = connection_manager
+                    .get(*actor_id)
+                    .map(|connection| connection.addr().is_none())
+                    .unwrap_or(false);
+
+                if internal {
+                    admin_responses.send(AdminResponse::new(
+                        command.source,
+                        format!("{:?} is internal and can't be kicked", actor_id),
+                    ));
+                    continue;
+                }
+
+                let banned = if let AdminCommandKind::Ban(_) = &command.kind {
+                    if let Some(connection) = connection_manager.get(*actor_id) {
+                        if let Some(addr) = connection.addr() {
+                            access_control.ban_addr(addr.ip());
+                        }
+
+                        if let Some(player_id) = connection.actor().player_id() {
+                            access_control.ban_player(player_id);
+                        }
+                    }
+
+                    true
+                } else {
+                    false
+                };
+
+                match connection_manager.disconnect(*actor_id, DisconnectReason::Kicked) {
+                    Some(event) => {
+                        connection_events.send(event);
+
+                        if banned {
+                            format!("banned {:?}", actor_id)
+                        } else {
+                            format!("kicked {:?}", actor_id)
+                        }
+                    }
+                    None => format!("no connection for {:?}", actor_id),
+                }
+            }
+            AdminCommandKind::Broadcast(text) => {
+                network_handle.add_payload(
+                    NetworkTarget::All,
+                    Payload::Admin(AdminPayload::Broadcast(text.clone())),
+                );
+
+                "sent".to_string()
+            }
+            AdminCommandKind::Shutdown => {
+                app_exit_events.send(AppExit);
+
+                "shutting down".to_string()
+            }
+            AdminCommandKind::Teleport { .. } | AdminCommandKind::Spawn { .. } => continue,
+        };
+
+        admin_responses.send(AdminResponse::new(command.source, response));
+    }
+}
+
+pub fn admin_response_system(
+    mut event_reader: Local<EventReader<AdminResponse>>,
+    admin_responses: Res<Events<AdminResponse>>,
+    mut network_handle: ResMut<NetworkHandle>,
+) {
+    for response in event_reader.iter(&admin_responses) {
+        match response.source {
+            AdminSource::Console => println!("{}", response.text),
+            AdminSource::Actor(actor_id) => network_handle.add_payload(
+                NetworkTarget::ActorId(actor_id),
+                Payload::Admin(AdminPayload::Response(response.text.clone())),
+            ),
+        }
+    }
+}
+
+/// Logs broadcasts and admin responses from the server.
+pub fn admin_message_system(
+    mut event_reader: Local<EventReader<Message>>,
+    messages: Res<Events<Message>>,
+) {
+    for message in event_reader.iter(&messages) {
+        match &message.payload {
+            Payload::Admin(AdminPayload::Broadcast(text)) => info!("[broadcast] {}", text),
+            Payload::Admin(AdminPayload::Response(text)) => info!("{}", text),
+            _ => (),
+        }
+    }
+}
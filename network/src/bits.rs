@@ -0,0 +1,119 @@
+/// Packs values into as few bits as they need, least significant bit first.
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bit: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the low `bits` bits of `value`, at most 64.
+    pub fn write_bits(&mut self, value: u64, bits: u32) {
+        for i in 0..bits {
+            if self.bit == 0 {
+                self.bytes.push(0);
+            }
+
+            if value >> i & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 1 << self.bit;
+            }
+
+            self.bit = (self.bit + 1) % 8;
+        }
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_bits(value as u64, 1);
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bits(value.to_bits() as u64, 32);
+    }
+
+    /// Writes `value` rounded to the nearest step of `quantization`, clamped to its range.
+    pub fn write_quantized(&mut self, value: f32, quantization: Quantization) {
+        self.write_bits(quantization.quantize(value), quantization.bits);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn read_bits(&mut self, bits: u32) -> Result<u64, crate::Error> {
+        if self.position + bits as usize > self.bytes.len() * 8 {
+            return Err(crate::Error::UnexpectedEnd);
+        }
+
+        let mut value = 0;
+
+        for i in 0..bits {
+            let byte = self.bytes[self.position / 8];
+
+            if byte >> (self.position % 8) & 1 == 1 {
+                value |= 1 << i;
+            }
+
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, crate::Error> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, crate::Error> {
+        Ok(f32::from_bits(self.read_bits(32)? as u32))
+    }
+
+    pub fn read_quantized(&mut self, quantization: Quantization) -> Result<f32, crate::Error> {
+        Ok(quantization.dequantize(self.read_bits(quantization.bits)?))
+    }
+}
+
+/// Maps floats in `origin..origin + step * 2^bits` onto `bits` bit integers, `bits` below 64.
+#[derive(Clone, Copy, Debug)]
+pub struct Quantization {
+    pub origin: f32,
+    pub step: f32,
+    pub bits: u32,
+}
+
+impl Quantization {
+    /// Range centered on `center`.
+    pub fn centered(center: f32, step: f32, bits: u32) -> Self {
+        Self {
+            origin: center - step * (1u64 << (bits - 1)) as f32,
+            step,
+            bits,
+        }
+    }
+
+    pub fn quantize(&self, value: f32) -> u64 {
+        let max = ((1u64 << self.bits) - 1) as f32;
+
+        ((value - self.origin) / self.step)
+            .round()
+            .max(0.0)
+            .min(max) as u64
+    }
+
+    pub fn dequantize(&self, value: u64) -> f32 {
+        self.origin + value as f32 * self.step
+    }
+}
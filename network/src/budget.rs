@@ -0,0 +1,144 @@
+use std::time::Instant;
+
+/// What happens when a connection goes over its [`InboundBudget`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BudgetAction {
+    /// Drops the payloads that don't fit.
+    Drop,
+    Disconnect,
+}
+
+/// How much a single connection may send, refilled continuously up to a second's worth.
+///
+/// Bytes are counted on the wire as frames arrive, before they're decrypted or decoded, plus
+/// whatever they grow by when decompressed. A frame larger than `bytes_per_second` never fits.
+#[derive(Clone, Debug)]
+pub struct InboundBudget {
+    pub payloads_per_second: u32,
+    pub bytes_per_second: u64,
+    pub action: BudgetAction,
+}
+
+impl Default for InboundBudget {
+    fn default() -> Self {
+        Self {
+            payloads_per_second: 600,
+            bytes_per_second: 256 * 1024,
+            action: BudgetAction::Drop,
+        }
+    }
+}
+
+pub struct BudgetTracker {
+    budget: InboundBudget,
+    payloads: f64,
+    bytes: f64,
+    last_refill: Instant,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: InboundBudget) -> Self {
+        Self {
+            payloads: budget.payloads_per_second as f64,
+            bytes: budget.bytes_per_second as f64,
+            budget,
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn action(&self) -> BudgetAction {
+        self.budget.action
+    }
+
+    /// The most that's read from the socket in one go, the rest waits in the kernel.
+    pub fn max_read(&self) -> usize {
+        self.budget.bytes_per_second as usize
+    }
+
+    /// Bytes that can still be spent right now.
+    pub fn bytes(&mut self) -> usize {
+        self.refill();
+        self.bytes as usize
+    }
+
+    /// Returns false without spending anything if `bytes` don't fit.
+    pub fn spend_bytes(&mut self, bytes: usize) -> bool {
+        self.refill();
+
+        if self.bytes < bytes as f64 {
+            return false;
+        }
+
+        self.bytes -= bytes as f64;
+
+        true
+    }
+
+    /// Returns false without spending anything if another payload doesn't fit.
+    pub fn spend_payload(&mut self) -> bool {
+        self.refill();
+
+        if self.payloads < 1.0 {
+            return false;
+        }
+
+        self.payloads -= 1.0;
+
+        true
+    }
+
+    fn refill(&mut self) {
+        let elapsed = self.last_refill.elapsed().as_secs_f64();
+        self.last_refill = Instant::now();
+
+        let payloads_per_second = self.budget.payloads_per_second as f64;
+        let bytes_per_second = self.budget.bytes_per_second as f64;
+
+        self.payloads = (self.payloads + elapsed * payloads_per_second).min(payloads_per_second);
+        self.bytes = (self.bytes + elapsed * bytes_per_second).min(bytes_per_second);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    fn tracker(payloads_per_second: u32, bytes_per_second: u64) -> BudgetTracker {
+        BudgetTracker::new(InboundBudget {
+            payloads_per_second,
+            bytes_per_second,
+            action: BudgetAction::Drop,
+        })
+    }
+
+    #[test]
+    fn spending_stops_at_the_budget() {
+        let mut tracker = tracker(2, 100);
+
+        assert!(tracker.spend_bytes(60));
+        assert!(!tracker.spend_bytes(60));
+        assert!(tracker.spend_bytes(40));
+
+        assert!(tracker.spend_payload());
+        assert!(tracker.spend_payload());
+        assert!(!tracker.spend_payload());
+    }
+
+    #[test]
+    fn budgets_refill_up_to_a_second() {
+        let mut tracker = tracker(10, 1000);
+
+        assert!(tracker.spend_bytes(1000));
+        thread::sleep(Duration::from_millis(100));
+
+        let refilled = tracker.bytes();
+        assert!(refilled >= 90 && refilled < 1000, "{}", refilled);
+
+        let mut tracker = self::tracker(10, 1000);
+        thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(tracker.bytes(), 1000);
+        assert!(!tracker.spend_bytes(1001));
+    }
+}
@@ -0,0 +1,339 @@
+use crate::*;
+use bevy::prelude::*;
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Connecting and handshaking give up after this long.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait between attempts, doubling every failed attempt.
+#[derive(Clone, Debug)]
+pub struct Reconnect {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Attempts in a row before giving up, `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            max_attempts: Some(10),
+        }
+    }
+}
+
+impl Reconnect {
+    /// The delay before `attempt`, starting at 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::default();
+        }
+
+        self.initial_delay
+            .checked_mul(2u32.saturating_pow(attempt - 2))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClientConnectionState {
+    /// Nothing to connect to yet, see [`ClientConnection::connect`].
+    Idle,
+    Connecting {
+        attempt: u32,
+    },
+    Handshaking {
+        attempt: u32,
+    },
+    Connected,
+    /// Waiting to retry after failing to connect or losing the connection.
+    Reconnecting {
+        attempt: u32,
+        retry_at: Instant,
+    },
+    /// Gave up, see [`ClientConnection::failure`].
+    Failed,
+}
+
+pub(crate) enum Progress {
+    Handshaking,
+    Done(Result<(ConnectionManager, ConnectionEvent), crate::Error>),
+}
+
+/// The client's connection to the server, reconnects with [`NetworkSettings::reconnect`].
+pub struct ClientConnection {
+    state: ClientConnectionState,
+    addr: Option<String>,
+    server: Option<ActorId>,
+    failure: Option<String>,
+    progress: Option<Mutex<mpsc::Receiver<Progress>>>,
+}
+
+impl Default for ClientConnection {
+    fn default() -> Self {
+        Self {
+            state: ClientConnectionState::Idle,
+            addr: None,
+            server: None,
+            failure: None,
+            progress: None,
+        }
+    }
+}
+
+impl ClientConnection {
+    /// Already connected to `server` at `addr`, like after [`NetworkPlugin::client`].
+    pub fn connected(addr: impl Into<String>, server: ActorId) -> Self {
+        Self {
+            state: ClientConnectionState::Connected,
+            addr: Some(addr.into()),
+            server: Some(server),
+            ..Self::default()
+        }
+    }
+
+    /// Starts connecting to `addr` with the next update, unless already connected.
+    pub fn connect(&mut self, addr: impl Into<String>) {
+        if let ClientConnectionState::Connected = self.state {
+            return;
+        }
+
+        self.addr = Some(addr.into());
+        self.state = ClientConnectionState::Connecting { attempt: 1 };
+        self.failure = None;
+        self.progress = None;
+    }
+
+    /// Moves on to `addr` right away, the old connection is expected to close.
+    pub fn redirect(&mut self, addr: impl Into<String>) {
+        self.server = None;
+        self.state = ClientConnectionState::Idle;
+        self.connect(addr);
+    }
+
+    pub fn state(&self) -> ClientConnectionState {
+        self.state
+    }
+
+    pub fn addr(&self) -> Option<&str> {
+        self.addr.as_deref()
+    }
+
+    /// The actor of the server we're connected to.
+    pub fn server(&self) -> Option<ActorId> {
+        self.server
+    }
+
+    /// Why the last attempt or connection failed.
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    fn fail(&mut self, attempt: u32, cause: &DisconnectCause, reconnect: Option<&Reconnect>) {
+        log::warn!("Connection to {:?} failed: {:?}", self.addr, cause);
+
+        self.progress = None;
+        self.state = match reconnect {
+            Some(reconnect)
+                if retries(cause)
+                    && reconnect
+                        .max_attempts
+                        .map(|max_attempts| attempt < max_attempts)
+                        .unwrap_or(true) =>
+            {
+                ClientConnectionState::Reconnecting {
+                    attempt: attempt + 1,
+                    retry_at: Instant::now() + reconnect.delay(attempt + 1),
+                }
+            }
+            _ => ClientConnectionState::Failed,
+        };
+        self.failure = Some(format!("{:?}", cause));
+    }
+}
+
+/// Whether trying again could end differently.
+fn retries(cause: &DisconnectCause) -> bool {
+    let reason = match cause {
+        DisconnectCause::Error(crate::Error::Goodbye(reason)) => reason,
+        DisconnectCause::Error(crate::Error::LoginRejected(_))
+        | DisconnectCause::Error(crate::Error::VersionMismatch { .. })
+        | DisconnectCause::Error(crate::Error::TickMismatch { .. })
+        | DisconnectCause::Error(crate::Error::EncryptionRequired) => return false,
+        DisconnectCause::Error(_) => return true,
+        DisconnectCause::Local(reason) | DisconnectCause::Remote(reason) => reason,
+    };
+
+    match reason {
+        DisconnectReason::ServerShutdown
+        | DisconnectReason::Timeout
+        | DisconnectReason::Rejected(Rejection::RateLimited)
+        | DisconnectReason::Rejected(Rejection::ServerFull) => true,
+        _ => false,
+    }
+}
+
+/// Connects and handshakes on another thread, the blocking handshake would stall the app.
+/// `claim` is the actor type we want to be known as, see [`NetworkSettings::peer_tys`].
+pub(crate) fn start_attempt(
+    addr: String,
+    connection_manager: ConnectionManager,
+    network_settings: NetworkSettings,
+    actor_ty: ActorTy,
+    claim: Option<ActorTy>,
+) -> mpsc::Receiver<Progress> {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let mut connection_manager = connection_manager;
+
+        let mut attempt = || -> Result<_, crate::Error> {
+            let addr = addr
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable))?;
+
+            let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+            stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+
+            let _ = sender.send(Progress::Handshaking);
+
+            let mut handshake = Handshake::new(HandshakeActorIds::None, &network_settings);
+            handshake.actor_ty = claim;
+
+            connection_manager.add_connection(stream, actor_ty, handshake, &network_settings, None)
+        };
+
+        let result = attempt().map(|event| (connection_manager, event));
+        let _ = sender.send(Progress::Done(result));
+    });
+
+    receiver
+}
+
+pub fn client_connection_system(
+    mut client_connection: ResMut<ClientConnection>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    network_settings: Res<NetworkSettings>,
+    mut recorder: ResMut<Recorder>,
+    mut event_reader: Local<EventReader<ConnectionEvent>>,
+    mut connection_events: ResMut<Events<ConnectionEvent>>,
+) {
+    let reconnect = network_settings.reconnect.as_ref();
+
+    for event in event_reader.iter(&connection_events) {
+        if let ConnectionEvent::Disconnected { actor, cause, .. } = event {
+            if client_connection.state != ClientConnectionState::Connected
+                || Some(actor.id()) != client_connection.server
+            {
+                continue;
+            }
+
+            client_connection.server = None;
+
+            // whatever we closed ourselves, except for timeouts, stays closed
+            match cause {
+                // the redirect itself is another message, it takes over from here
+                DisconnectCause::Remote(DisconnectReason::Redirected) => {
+                    client_connection.state = ClientConnectionState::Idle;
+                }
+                DisconnectCause::Local(reason) if *reason != DisconnectReason::Timeout => {
+                    client_connection.state = ClientConnectionState::Failed;
+                    client_connection.failure = Some(format!("{:?}", cause));
+                }
+                cause => client_connection.fail(0, cause, reconnect),
+            }
+        }
+    }
+
+    match client_connection.state {
+        ClientConnectionState::Reconnecting { attempt, retry_at } if Instant::now() >= retry_at => {
+            client_connection.state = ClientConnectionState::Connecting { attempt };
+        }
+        _ => (),
+    }
+
+    let attempt = match client_connection.state {
+        ClientConnectionState::Connecting { attempt }
+        | ClientConnectionState::Handshaking { attempt } => attempt,
+        _ => return,
+    };
+
+    if client_connection.progress.is_none() {
+        let addr = match &client_connection.addr {
+            Some(addr) => addr.clone(),
+            None => return,
+        };
+
+        log::info!("Connecting to {} (attempt {})", addr, attempt);
+
+        let receiver = start_attempt(
+            addr,
+            connection_manager.detached(),
+            network_settings.clone(),
+            network_settings.connection_ty,
+            None,
+        );
+
+        client_connection.progress = Some(Mutex::new(receiver));
+    }
+
+    let progress: Vec<_> = match &client_connection.progress {
+        Some(receiver) => receiver.lock().unwrap().try_iter().collect(),
+        None => Vec::new(),
+    };
+
+    for progress in progress {
+        match progress {
+            Progress::Handshaking => {
+                client_connection.state = ClientConnectionState::Handshaking { attempt };
+            }
+            Progress::Done(Ok((connected, event))) => {
+                let event = connection_manager.attach(connected, event);
+                recorder.record_format(connection_manager.wire_format());
+
+                if let ConnectionEvent::Connected { actor, .. } = &event {
+                    client_connection.server = Some(actor.id());
+                }
+
+                client_connection.state = ClientConnectionState::Connected;
+                client_connection.failure = None;
+                client_connection.progress = None;
+
+                connection_events.send(event);
+            }
+            Progress::Done(Err(e)) => {
+                client_connection.fail(attempt, &DisconnectCause::Error(e), reconnect);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_attempts_are_immediate() {
+        assert_eq!(Reconnect::default().delay(0), Duration::default());
+        assert_eq!(Reconnect::default().delay(1), Duration::default());
+    }
+
+    #[test]
+    fn delays_double_up_to_the_cap() {
+        let reconnect = Reconnect::default();
+
+        assert_eq!(reconnect.delay(2), Duration::from_millis(500));
+        assert_eq!(reconnect.delay(3), Duration::from_secs(1));
+        assert_eq!(reconnect.delay(4), Duration::from_secs(2));
+        assert_eq!(reconnect.delay(7), Duration::from_secs(10));
+        assert_eq!(reconnect.delay(u32::MAX), Duration::from_secs(10));
+    }
+}
@@ -3,11 +3,19 @@ use bevy::prelude::*;
 
 pub fn receiving_system(
     mut connection_manager: ResMut<ConnectionManager>,
+    mut network_tick: ResMut<NetworkTick>,
+    mut recorder: ResMut<Recorder>,
+    mut network_log: ResMut<NetworkLog>,
     mut message_events: ResMut<Events<Message>>,
     mut connection_events_resource: ResMut<Events<ConnectionEvent>>,
 ) {
+    network_tick.0 += 1;
+
     let (messages, connection_events) = connection_manager.receive();
 
+    recorder.record_incoming(*network_tick, &messages);
+    network_log.record_incoming(*network_tick, &messages);
+
     message_events.extend(messages.into_iter());
     connection_events_resource.extend(connection_events.into_iter());
 }
@@ -18,15 +26,25 @@ pub fn sending_system(
     mut connection_events: ResMut<Events<ConnectionEvent>>,
     mut network_entity_registry: ResMut<NetworkEntityRegistry>,
     mut spawn_manager: ResMut<SpawnManager>,
+    mut recorder: ResMut<Recorder>,
+    mut network_log: ResMut<NetworkLog>,
+    network_tick: Res<NetworkTick>,
+    rooms: Res<Rooms>,
 ) {
     network_handle.convert_spawn_messages(
         &mut *network_entity_registry,
         &mut *spawn_manager,
         &*connection_manager,
+        &*rooms,
     );
 
     let payloads = network_handle.clear_payloads();
-    let events = connection_manager.send(payloads);
+
+    recorder.record_outgoing(*network_tick, &payloads);
+    recorder.flush();
+    network_log.record_outgoing(*network_tick, &payloads);
+
+    let events = connection_manager.send(payloads, &*rooms);
 
     connection_events.extend(events.into_iter());
 }
@@ -0,0 +1,197 @@
+use crate::*;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// How `Transform`s synced with [`AppBuilderExt::add_compact_transform_sync`] are quantized,
+/// both ends have to use the same.
+#[derive(Clone, Copy, Debug)]
+pub struct CompactTransformSettings {
+    pub x: Quantization,
+    pub y: Quantization,
+    pub z: Quantization,
+    /// Only the angle around the z axis is kept, the game is 2D.
+    pub rotation: Quantization,
+}
+
+impl Default for CompactTransformSettings {
+    /// Within about 16000 units of the world origin on x and y and 512 on z, to a 64th of a unit.
+    fn default() -> Self {
+        let horizontal = Quantization::centered(0.0, 1.0 / 64.0, 21);
+
+        Self {
+            x: horizontal,
+            y: horizontal,
+            z: Quantization::centered(0.0, 1.0 / 64.0, 16),
+            rotation: Quantization::centered(0.0, std::f32::consts::PI / 32768.0, 16),
+        }
+    }
+}
+
+impl CompactTransformSettings {
+    /// Leaves out the scale if it's `last_scale`.
+    pub fn encode(&self, transform: &Transform, last_scale: Option<Vec3>) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+
+        writer.write_quantized(transform.translation.x, self.x);
+        writer.write_quantized(transform.translation.y, self.y);
+        writer.write_quantized(transform.translation.z, self.z);
+
+        let angle = 2.0 * transform.rotation.z.atan2(transform.rotation.w);
+        let angle = (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::PI * 2.0)
+            - std::f32::consts::PI;
+        writer.write_quantized(angle, self.rotation);
+
+        let scaled = last_scale != Some(transform.scale);
+        writer.write_bool(scaled);
+
+        if scaled {
+            writer.write_f32(transform.scale.x);
+            writer.write_f32(transform.scale.y);
+            writer.write_f32(transform.scale.z);
+        }
+
+        writer.finish()
+    }
+
+    /// A scale that was left out is taken from `current`.
+    pub fn decode(&self, bytes: &[u8], current: &Transform) -> Result<Transform, crate::Error> {
+        let mut reader = BitReader::new(bytes);
+
+        let translation = Vec3::new(
+            reader.read_quantized(self.x)?,
+            reader.read_quantized(self.y)?,
+            reader.read_quantized(self.z)?,
+        );
+
+        let rotation = Quat::from_rotation_z(reader.read_quantized(self.rotation)?);
+
+        let scale = if reader.read_bool()? {
+            Vec3::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?)
+        } else {
+            current.scale
+        };
+
+        Ok(Transform {
+            translation,
+            rotation,
+            scale,
+        })
+    }
+}
+
+/// The network type of compactly synced `Transform`s, so an end that syncs them with reflection
+/// refuses them instead of misreading them.
+pub struct CompactTransform;
+network_uuid!(CompactTransform = 238430592683408613690154);
+
+pub fn apply_compact_transform_update(
+    world: &mut World,
+    resources: &Resources,
+    entity: Entity,
+    sender: &Actor,
+    data: &[u8],
+) {
+    let rooms = resources.get::<Rooms>().unwrap();
+    let settings = resources.get::<CompactTransformSettings>().unwrap();
+
+    if let Ok(component_sync) = world.get::<ComponentSync<Transform>>(entity) {
+        if !sender.targeted_by(&component_sync.ownership, &*rooms) {
+            error!("Asked to update component, by invalid sender {:?}!", sender);
+            return;
+        }
+    } else {
+        return;
+    }
+
+    if let Ok(mut transform) = world.get_mut::<Transform>(entity) {
+        match settings.decode(data, &transform) {
+            Ok(value) => *transform = value,
+            Err(e) => error!("Failed to decode Transform from {:?}: {:?}", sender, e),
+        }
+    }
+}
+
+/// Snapshots always carry the scale.
+pub fn snapshot_compact_transform(
+    world: &World,
+    resources: &Resources,
+    entity: Entity,
+) -> Option<Vec<u8>> {
+    let settings = resources.get::<CompactTransformSettings>().unwrap();
+
+    world
+        .get::<Transform>(entity)
+        .ok()
+        .map(|transform| settings.encode(&transform, None))
+}
+
+pub fn restore_compact_transform(
+    world: &mut World,
+    resources: &Resources,
+    entity: Entity,
+    data: &[u8],
+) {
+    let settings = resources.get::<CompactTransformSettings>().unwrap();
+
+    if let Ok(mut transform) = world.get_mut::<Transform>(entity) {
+        match settings.decode(data, &transform) {
+            Ok(value) => *transform = value,
+            Err(e) => error!("Failed to restore Transform: {:?}", e),
+        }
+    }
+}
+
+/// Like [`component_sync_sending_system`], but only sends scales that changed since the last
+/// update of the entity. Every scale is sent again once someone connects, and entities that are
+/// gone are forgotten.
+pub fn compact_transform_sending_system(
+    mut network_handle: ResMut<NetworkHandle>,
+    connection_manager: Res<ConnectionManager>,
+    network_settings: Res<NetworkSettings>,
+    settings: Res<CompactTransformSettings>,
+    rooms: Res<Rooms>,
+    mut last_scales: Local<HashMap<NetworkEntity, Vec3>>,
+    mut event_reader: Local<EventReader<ConnectionEvent>>,
+    events: Res<Events<ConnectionEvent>>,
+    mut query: Query<(&Transform, &mut ComponentSync<Transform>, &NetworkEntity)>,
+) {
+    for connection_event in event_reader.iter(&events) {
+        if let ConnectionEvent::Connected { .. } = connection_event {
+            last_scales.clear();
+        }
+    }
+
+    let mut present = HashSet::new();
+
+    for (transform, mut component_sync, network_entity) in query.iter_mut() {
+        present.insert(*network_entity);
+
+        if !component_sync.should_sync {
+            continue;
+        }
+
+        component_sync.should_sync = false;
+
+        if let Some(actor) = connection_manager.get_local_actor() {
+            if !actor.targeted_by(&component_sync.ownership, &*rooms) {
+                continue;
+            }
+
+            let last_scale = last_scales.insert(*network_entity, transform.scale);
+            let bytes = settings.encode(transform, last_scale);
+
+            for target in &network_settings.sync_components_with {
+                network_handle.sync_component(
+                    target.clone(),
+                    *network_entity,
+                    CompactTransform::UUID,
+                    bytes.clone(),
+                );
+            }
+        } else {
+            error!("Local actor not found!");
+        }
+    }
+
+    last_scales.retain(|network_entity, _| present.contains(network_entity));
+}
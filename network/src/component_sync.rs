@@ -2,8 +2,8 @@ use crate::*;
 use bevy::{prelude::*, reflect::TypeRegistry};
 
 pub struct ComponentSync<T: SyncableComponent> {
-    should_sync: bool,
-    ownership: NetworkTarget,
+    pub(crate) should_sync: bool,
+    pub(crate) ownership: NetworkTarget,
     phantom_data: std::marker::PhantomData<T>,
 }
 
@@ -33,44 +33,118 @@ impl<T: SyncableComponent> ComponentSync<T> {
     }
 }
 
-pub fn component_sync_receiving_system<T: SyncableComponent + Send + Sync + 'static>(
-    network_entity_registry: Res<NetworkEntityRegistry>,
-    mut event_reader: Local<EventReader<Message>>,
-    events: Res<Events<Message>>,
-    type_registry: Res<TypeRegistry>,
-    mut query: Query<(&mut T, &ComponentSync<T>), With<NetworkEntity>>,
+/// Applies an update if `sender` owns the component, used as the [`ComponentUpdateHandler`] of
+/// every synced component.
+pub fn apply_component_update<T: SyncableComponent + Send + Sync + 'static>(
+    world: &mut World,
+    resources: &Resources,
+    entity: Entity,
+    sender: &Actor,
+    data: &[u8],
 ) {
-    for message in event_reader.iter(&events) {
-        if let Payload::ComponentUpdate {
-            target_entity,
-            network_type_uuid,
-            data,
-        } = &message.payload
-        {
-            if *network_type_uuid != T::UUID {
-                continue;
-            }
+    let rooms = resources.get::<Rooms>().unwrap();
+    let type_registry = resources.get::<TypeRegistry>().unwrap();
+    let wire_format = resources.get::<ConnectionManager>().unwrap().wire_format();
+
+    if let Ok(component_sync) = world.get::<ComponentSync<T>>(entity) {
+        if !sender.targeted_by(&component_sync.ownership, &*rooms) {
+            error!("Asked to update component, by invalid sender {:?}!", sender);
+            return;
+        }
+    } else {
+        return;
+    }
 
-            let entity = if let Some(entity) = network_entity_registry.get(target_entity) {
-                entity
-            } else {
-                error!("Could not find entity! {:?}", target_entity);
-                continue;
-            };
-
-            if let Ok((mut component, component_sync)) = query.get_mut(*entity) {
-                if !message.sender.targeted_by(&component_sync.ownership) {
-                    error!(
-                        "Asked to update component, by invalid sender {:?}!",
-                        message.sender
-                    );
-                    continue;
-                }
+    if let Ok(mut component) = world.get_mut::<T>(entity) {
+        match T::from_bytes(data, &*type_registry, wire_format) {
+            Ok(value) => *component = value,
+            Err(e) => error!(
+                "Failed to decode {} from {:?}: {:?}",
+                std::any::type_name::<T>(),
+                sender,
+                e
+            ),
+        }
+    }
+}
 
-                *component = T::from_bytes(data, &*type_registry);
+pub fn snapshot_component<T: SyncableComponent + Send + Sync + 'static>(
+    world: &World,
+    resources: &Resources,
+    entity: Entity,
+) -> Option<Vec<u8>> {
+    let type_registry = resources.get::<TypeRegistry>().unwrap();
+    let wire_format = resources.get::<ConnectionManager>().unwrap().wire_format();
+
+    world
+        .get::<T>(entity)
+        .ok()
+        .map(|component| component.to_bytes(&*type_registry, wire_format))
+}
+
+pub fn restore_component<T: SyncableComponent + Send + Sync + 'static>(
+    world: &mut World,
+    resources: &Resources,
+    entity: Entity,
+    data: &[u8],
+) {
+    let type_registry = resources.get::<TypeRegistry>().unwrap();
+    let wire_format = resources.get::<ConnectionManager>().unwrap().wire_format();
+
+    if let Ok(mut component) = world.get_mut::<T>(entity) {
+        match T::from_bytes(data, &*type_registry, wire_format) {
+            Ok(value) => *component = value,
+            Err(e) => error!("Failed to restore {}: {:?}", std::any::type_name::<T>(), e),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ComponentUpdateEventReader {
+    reader: EventReader<Message>,
+}
+
+/// Routes every `ComponentUpdate` to the handler registered for its type.
+pub fn component_update_system(world: &mut World, resources: &mut Resources) {
+    let mut updates = Vec::new();
+
+    {
+        let network_entity_registry = resources.get::<NetworkEntityRegistry>().unwrap();
+        let mut event_reader = resources.get_mut::<ComponentUpdateEventReader>().unwrap();
+        let events = resources.get::<Events<Message>>().unwrap();
+
+        for message in event_reader.reader.iter(&events) {
+            if let Payload::ComponentUpdate {
+                target_entity,
+                network_type_uuid,
+                data,
+            } = &message.payload
+            {
+                if let Some(entity) = network_entity_registry.get(target_entity) {
+                    updates.push((
+                        *entity,
+                        *network_type_uuid,
+                        message.sender.clone(),
+                        data.clone(),
+                    ));
+                } else {
+                    error!("Could not find entity! {:?}", target_entity);
+                }
             }
         }
     }
+
+    let network_type_registry = resources.get::<NetworkTypeRegistry>().unwrap();
+
+    for (entity, network_type_uuid, sender, data) in updates {
+        match network_type_registry
+            .get(&network_type_uuid)
+            .and_then(|network_type| network_type.component_update_handler)
+        {
+            Some(handler) => handler(world, resources, entity, &sender, &data),
+            None => error!("No component registered for {:?}", network_type_uuid),
+        }
+    }
 }
 
 pub fn component_sync_connect_system<T: SyncableComponent + Send + Sync + 'static>(
@@ -100,6 +174,7 @@ pub fn component_sync_sending_system<T: SyncableComponent + Send + Sync + 'stati
     type_registry: Res<TypeRegistry>,
     connection_manager: Res<ConnectionManager>,
     network_settings: Res<NetworkSettings>,
+    rooms: Res<Rooms>,
     mut query: Query<(&T, &mut ComponentSync<T>, &NetworkEntity)>,
 ) {
     for (component, mut component_sync, network_entity) in query.iter_mut() {
@@ -110,11 +185,11 @@ pub fn component_sync_sending_system<T: SyncableComponent + Send + Sync + 'stati
         component_sync.should_sync = false;
 
         if let Some(actor) = connection_manager.get_local_actor() {
-            if !actor.targeted_by(&component_sync.ownership) {
+            if !actor.targeted_by(&component_sync.ownership, &*rooms) {
                 continue;
             }
 
-            let bytes = component.to_bytes(&*type_registry);
+            let bytes = component.to_bytes(&*type_registry, connection_manager.wire_format());
 
             for target in &network_settings.sync_components_with {
                 network_handle.sync_component(
@@ -0,0 +1,76 @@
+use flate2::{read::DeflateDecoder, write::DeflateEncoder};
+use serde::{Deserialize, Serialize};
+use std::io::prelude::*;
+
+pub const UNCOMPRESSED: u8 = 0;
+pub const DEFLATE: u8 = 1;
+
+pub const MAX_DECOMPRESSED_LEN: usize = 1 << 26;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompressionSettings {
+    /// Frames smaller than this many bytes are sent uncompressed.
+    pub threshold: usize,
+    pub level: u32,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1024,
+            level: 6,
+        }
+    }
+}
+
+/// Prefixes `bytes` with a flag byte, compressing them if `compression` is set and they're
+/// large enough to be worth it.
+pub fn compress_frame(
+    bytes: &[u8],
+    compression: Option<&CompressionSettings>,
+) -> Result<Vec<u8>, crate::Error> {
+    if let Some(compression) = compression {
+        if bytes.len() >= compression.threshold {
+            let mut encoder =
+                DeflateEncoder::new(vec![DEFLATE], flate2::Compression::new(compression.level));
+            encoder.write_all(bytes)?;
+            let compressed = encoder.finish()?;
+
+            if compressed.len() < bytes.len() + 1 {
+                return Ok(compressed);
+            }
+        }
+    }
+
+    let mut frame = Vec::with_capacity(bytes.len() + 1);
+    frame.push(UNCOMPRESSED);
+    frame.extend_from_slice(bytes);
+    Ok(frame)
+}
+
+pub fn decompress_frame(frame: &[u8]) -> Result<Vec<u8>, crate::Error> {
+    decompress_frame_limited(frame, MAX_DECOMPRESSED_LEN)
+}
+
+/// Like [`decompress_frame`], but gives up once more than `max_len` bytes come out.
+pub fn decompress_frame_limited(frame: &[u8], max_len: usize) -> Result<Vec<u8>, crate::Error> {
+    let max_len = max_len.min(MAX_DECOMPRESSED_LEN);
+
+    match frame.split_first() {
+        Some((&UNCOMPRESSED, bytes)) => Ok(bytes.to_vec()),
+        Some((&DEFLATE, bytes)) => {
+            let mut decompressed = Vec::new();
+
+            DeflateDecoder::new(bytes)
+                .take(max_len as u64 + 1)
+                .read_to_end(&mut decompressed)?;
+
+            if decompressed.len() > max_len {
+                return Err(crate::Error::FrameTooLarge(decompressed.len() as u64));
+            }
+
+            Ok(decompressed)
+        }
+        _ => Err(crate::Error::InvalidFrame),
+    }
+}
@@ -0,0 +1,169 @@
+use crate::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Simulates a bad connection on outgoing payloads, delays are counted in network ticks so a
+/// given seed always plays out the same way.
+#[derive(Clone, Debug)]
+pub struct LinkConditioner {
+    pub latency_ticks: u32,
+    /// Up to this many ticks are randomly added to the latency.
+    pub jitter_ticks: u32,
+    /// Chance of dropping a tick's payloads.
+    pub loss: f32,
+    pub duplication: f32,
+    /// Chance of holding a tick's payloads back long enough for later ones to overtake them.
+    pub reordering: f32,
+    pub seed: u64,
+}
+
+impl Default for LinkConditioner {
+    fn default() -> Self {
+        Self {
+            latency_ticks: 0,
+            jitter_ticks: 0,
+            loss: 0.0,
+            duplication: 0.0,
+            reordering: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+pub struct ConditionedLink {
+    conditioner: LinkConditioner,
+    rng: StdRng,
+    tick: u64,
+    in_flight: Vec<(u64, Vec<Payload>)>,
+}
+
+impl ConditionedLink {
+    /// Chances outside of 0 to 1 are clamped, NaN counts as 0.
+    pub fn new(mut conditioner: LinkConditioner, connection_id: ConnectionId) -> Self {
+        conditioner.loss = chance(conditioner.loss);
+        conditioner.duplication = chance(conditioner.duplication);
+        conditioner.reordering = chance(conditioner.reordering);
+
+        Self {
+            rng: StdRng::seed_from_u64(conditioner.seed.wrapping_add(connection_id.0)),
+            conditioner,
+            tick: 0,
+            in_flight: Vec::new(),
+        }
+    }
+
+    /// Takes this tick's payloads and returns the ones that should arrive now.
+    pub fn condition(&mut self, payloads: Vec<Payload>) -> Vec<Payload> {
+        self.tick += 1;
+
+        if !payloads.is_empty() && !self.rng.gen_bool(self.conditioner.loss as f64) {
+            if self.rng.gen_bool(self.conditioner.duplication as f64) {
+                let release_tick = self.release_tick();
+                self.in_flight.push((release_tick, payloads.clone()));
+            }
+
+            let release_tick = self.release_tick();
+            self.in_flight.push((release_tick, payloads));
+        }
+
+        // stable, so payloads released on the same tick keep their order
+        self.in_flight
+            .sort_by_key(|(release_tick, _)| *release_tick);
+
+        let due = self
+            .in_flight
+            .iter()
+            .take_while(|(release_tick, _)| *release_tick <= self.tick)
+            .count();
+
+        self.in_flight
+            .drain(..due)
+            .flat_map(|(_, payloads)| payloads)
+            .collect()
+    }
+
+    fn release_tick(&mut self) -> u64 {
+        let mut delay = self.conditioner.latency_ticks as u64;
+
+        if self.conditioner.jitter_ticks > 0 {
+            delay += self
+                .rng
+                .gen_range(0, self.conditioner.jitter_ticks as u64 + 1);
+        }
+
+        if self.rng.gen_bool(self.conditioner.reordering as f64) {
+            delay += self.conditioner.jitter_ticks as u64 + 1;
+        }
+
+        self.tick + delay
+    }
+}
+
+fn chance(chance: f32) -> f32 {
+    if chance > 0.0 {
+        chance.min(1.0)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditioner(seed: u64) -> LinkConditioner {
+        LinkConditioner {
+            latency_ticks: 2,
+            jitter_ticks: 3,
+            loss: 0.2,
+            duplication: 0.1,
+            reordering: 0.1,
+            seed,
+        }
+    }
+
+    /// What arrives on each of 200 ticks, one ping sent per tick.
+    fn play(conditioner: LinkConditioner) -> Vec<Vec<Payload>> {
+        let mut link = ConditionedLink::new(conditioner, ConnectionId(1));
+
+        (0..200)
+            .map(|id| link.condition(vec![Payload::Ping { id }]))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_plays_out_the_same() {
+        assert_eq!(play(conditioner(7)), play(conditioner(7)));
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        assert_ne!(play(conditioner(7)), play(conditioner(8)));
+    }
+
+    #[test]
+    fn seeded_links_drop_and_reorder() {
+        let ids: Vec<_> = play(conditioner(7))
+            .into_iter()
+            .flatten()
+            .map(|payload| match payload {
+                Payload::Ping { id } => id,
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert!(ids.len() < 200);
+        assert!(ids.windows(2).any(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    fn chances_out_of_range_are_clamped() {
+        let mut conditioner = conditioner(7);
+        conditioner.loss = 2.0;
+        conditioner.duplication = -1.0;
+        conditioner.reordering = f32::NAN;
+
+        assert!(play(conditioner)
+            .into_iter()
+            .all(|payloads| payloads.is_empty()));
+    }
+}
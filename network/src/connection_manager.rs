@@ -1,10 +1,10 @@
 use crate::*;
 use bevy::{prelude::*, reflect::Uuid};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    io::{self, prelude::*},
-    net::{SocketAddr, TcpListener, TcpStream},
+    collections::{HashMap, HashSet},
+    net::{Shutdown, SocketAddr, TcpStream},
+    time::{Duration, Instant},
 };
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -26,22 +26,33 @@ impl ActorTy {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Actor {
     id: ActorId,
     ty: ActorTy,
+    player_id: Option<PlayerId>,
 }
 
 impl Actor {
     pub fn new(id: ActorId, ty: ActorTy) -> Self {
-        Self { id, ty }
+        Self {
+            id,
+            ty,
+            player_id: None,
+        }
     }
 
-    pub fn targeted_by(&self, network_target: &NetworkTarget) -> bool {
+    pub fn with_player_id(mut self, player_id: Option<PlayerId>) -> Self {
+        self.player_id = player_id;
+        self
+    }
+
+    pub fn targeted_by(&self, network_target: &NetworkTarget, rooms: &Rooms) -> bool {
         match network_target {
             NetworkTarget::All => true,
             NetworkTarget::ActorId(actor_id) => self.id == *actor_id,
             NetworkTarget::ActorTy(actor_ty) => self.ty == *actor_ty,
+            NetworkTarget::Room(room) => rooms.contains(room, self.id),
         }
     }
 
@@ -52,6 +63,11 @@ impl Actor {
     pub fn ty(&self) -> ActorTy {
         self.ty
     }
+
+    /// Set if the actor logged in.
+    pub fn player_id(&self) -> Option<PlayerId> {
+        self.player_id
+    }
 }
 
 #[derive(Debug)]
@@ -59,19 +75,24 @@ pub enum ConnectionEvent {
     Connected {
         actor: Actor,
         connection_id: ConnectionId,
+        player_id: Option<PlayerId>,
+        /// The actor reclaimed an id it held before disconnecting.
+        resumed: bool,
     },
     Disconnected {
         actor: Actor,
         connection_id: ConnectionId,
-        cause: crate::Error,
+        cause: DisconnectCause,
     },
 }
 
 pub enum ConnectionInner {
     External {
         addr: SocketAddr,
-        stream: TcpStream,
-        next_len: Option<usize>,
+        stream: FramedStream,
+        compression: Option<CompressionSettings>,
+        cipher: Option<Cipher>,
+        wire_format: WireFormat,
     },
     Internal {
         payloads: Vec<Payload>,
@@ -79,16 +100,40 @@ pub enum ConnectionInner {
 }
 
 impl ConnectionInner {
-    pub fn send(&mut self, mut payloads: Vec<Payload>) -> Result<(), crate::Error> {
+    pub fn send(
+        &mut self,
+        mut payloads: Vec<Payload>,
+        statistics: &mut ConnectionStatistics,
+    ) -> Result<(), crate::Error> {
         match self {
-            ConnectionInner::External { stream, .. } => {
-                let bytes = serde_cbor::to_vec(&payloads)?;
-                let len = (bytes.len() as u64).to_be_bytes();
+            ConnectionInner::External {
+                stream,
+                compression,
+                cipher,
+                wire_format,
+                ..
+            } => {
+                if payloads.is_empty() {
+                    return stream.flush();
+                }
 
-                stream.write(&len)?;
-                stream.write(&bytes)?;
+                let bytes = wire_format.encode(&payloads)?;
+                let mut frame = compress_frame(&bytes, compression.as_ref())?;
 
-                Ok(())
+                if frame[0] != UNCOMPRESSED {
+                    statistics.compressed_frames_sent += 1;
+                }
+
+                if let Some(cipher) = cipher {
+                    frame = cipher.encrypt(&frame)?;
+                }
+
+                statistics.bytes_sent += (FRAME_HEADER_LEN + frame.len()) as u64;
+                statistics.uncompressed_bytes_sent += bytes.len() as u64;
+                statistics.frames_sent += 1;
+                statistics.payloads_sent += payloads.len() as u64;
+
+                stream.write_frame(&frame)
             }
             ConnectionInner::Internal {
                 payloads: internal_payloads,
@@ -100,75 +145,284 @@ impl ConnectionInner {
         }
     }
 
-    pub fn receive(&mut self) -> Result<Vec<Payload>, crate::Error> {
+    pub fn receive(
+        &mut self,
+        statistics: &mut ConnectionStatistics,
+        mut budget: Option<&mut BudgetTracker>,
+    ) -> Result<Vec<Payload>, crate::Error> {
         match self {
             ConnectionInner::External {
-                stream, next_len, ..
+                stream,
+                cipher,
+                wire_format,
+                ..
             } => {
-                let len = match next_len {
-                    Some(v) => *v,
-                    None => {
-                        let mut buf = [0u8; 8];
-
-                        stream.read(&mut buf)?;
-
-                        u64::from_be_bytes(buf) as usize
-                    }
-                };
-
-                if len > 10000 {
-                    warn!(len);
+                let mut payloads = Vec::new();
+                let max_read = budget
+                    .as_ref()
+                    .map(|budget| budget.max_read())
+                    .unwrap_or(usize::MAX);
+
+                for frame in stream.read_frames(max_read)? {
+                    statistics.bytes_received += (FRAME_HEADER_LEN + frame.len()) as u64;
+                    statistics.frames_received += 1;
+
+                    // charged before it's decrypted or decoded, a flood costs no more than reading
+                    let max_len = match &mut budget {
+                        Some(budget) => {
+                            if !budget.spend_bytes(FRAME_HEADER_LEN + frame.len()) {
+                                statistics.frames_dropped += 1;
+
+                                if budget.action() == BudgetAction::Disconnect {
+                                    return Err(crate::Error::BudgetExceeded);
+                                }
+
+                                if let Some(cipher) = cipher {
+                                    cipher.skip();
+                                }
+
+                                continue;
+                            }
+
+                            frame.len() + budget.bytes()
+                        }
+                        None => MAX_DECOMPRESSED_LEN,
+                    };
+
+                    let decompressed = match cipher {
+                        Some(cipher) => decompress_frame_limited(&cipher.decrypt(&frame)?, max_len),
+                        None => decompress_frame_limited(&frame, max_len),
+                    };
+
+                    let bytes = match (decompressed, &mut budget) {
+                        (Ok(bytes), Some(budget)) => {
+                            budget.spend_bytes(bytes.len().saturating_sub(frame.len()));
+                            bytes
+                        }
+                        (Ok(bytes), None) => bytes,
+                        // it grew by more than what's left of the budget
+                        (Err(crate::Error::FrameTooLarge(_)), Some(budget))
+                            if budget.action() == BudgetAction::Drop =>
+                        {
+                            statistics.frames_dropped += 1;
+                            continue;
+                        }
+                        (Err(crate::Error::FrameTooLarge(_)), Some(_)) => {
+                            return Err(crate::Error::BudgetExceeded)
+                        }
+                        (Err(e), _) => return Err(e),
+                    };
+
+                    let mut frame_payloads: Vec<Payload> = wire_format.decode(&bytes)?;
+
+                    statistics.uncompressed_bytes_received += bytes.len() as u64;
+                    statistics.payloads_received += frame_payloads.len() as u64;
+
+                    payloads.append(&mut frame_payloads);
                 }
 
-                let mut buf = vec![0u8; len];
-
-                match stream.read(&mut buf) {
-                    Ok(_) => *next_len = None,
-                    Err(e) => {
-                        *next_len = Some(len);
-                        return Err(e.into());
-                    }
-                }
-
-                Ok(serde_cbor::from_slice(&buf)?)
+                Ok(payloads)
             }
             ConnectionInner::Internal { payloads } => Ok(std::mem::replace(payloads, Vec::new())),
         }
     }
+
+    /// Blocks until everything buffered is written or a second passes, errors are ignored.
+    pub fn close(&mut self) {
+        if let ConnectionInner::External { stream, .. } = self {
+            let _ = stream.stream().set_nonblocking(false);
+            let _ = stream
+                .stream()
+                .set_write_timeout(Some(Duration::from_secs(1)));
+            let _ = stream.flush();
+            let _ = stream.stream().shutdown(Shutdown::Both);
+        }
+    }
 }
 
 pub struct Connection {
     inner: ConnectionInner,
     actor: Actor,
+    statistics: ConnectionStatistics,
+
+    /// Pings and pongs waiting for the next send.
+    control_payloads: Vec<Payload>,
+    ping: Option<(u64, Instant)>,
+    next_ping_id: u64,
+
+    link: Option<ConditionedLink>,
+    budget: Option<BudgetTracker>,
+
+    /// Set once the other end said goodbye.
+    goodbye: Option<DisconnectReason>,
+    last_received: Instant,
+    last_sent: Instant,
 }
 
 impl Connection {
-    pub fn send(&mut self, payloads: Vec<Payload>) -> Result<(), crate::Error> {
-        match self.inner.send(payloads) {
-            Ok(_) => Ok(()),
-            Err(crate::Error::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(()),
-            Err(e) => Err(e),
+    pub fn new(inner: ConnectionInner, actor: Actor) -> Self {
+        Self {
+            inner,
+            actor,
+            statistics: ConnectionStatistics::default(),
+
+            control_payloads: Vec::new(),
+            ping: None,
+            next_ping_id: 0,
+
+            link: None,
+            budget: None,
+
+            goodbye: None,
+            last_received: Instant::now(),
+            last_sent: Instant::now(),
+        }
+    }
+
+    pub fn with_link(mut self, link: Option<ConditionedLink>) -> Self {
+        self.link = link;
+        self
+    }
+
+    pub fn with_budget(mut self, budget: Option<InboundBudget>) -> Self {
+        self.budget = budget.map(BudgetTracker::new);
+        self
+    }
+
+    pub fn send(&mut self, mut payloads: Vec<Payload>) -> Result<(), crate::Error> {
+        payloads.append(&mut self.control_payloads);
+
+        if let Some(link) = &mut self.link {
+            payloads = link.condition(payloads);
+        }
+
+        for payload in &payloads {
+            self.statistics.count_sent(payload);
+        }
+
+        if !payloads.is_empty() {
+            self.last_sent = Instant::now();
         }
+
+        self.inner.send(payloads, &mut self.statistics)
     }
 
     pub fn receive(&mut self) -> Result<Vec<Payload>, crate::Error> {
-        let mut payloads = Vec::new();
+        let mut payloads = self
+            .inner
+            .receive(&mut self.statistics, self.budget.as_mut())?;
 
-        loop {
-            match self.inner.receive() {
-                Ok(mut v) => {
-                    payloads.append(&mut v);
+        if !payloads.is_empty() {
+            self.last_received = Instant::now();
+        }
 
-                    if let ConnectionInner::Internal { .. } = self.inner {
-                        return Ok(payloads);
-                    }
-                }
-                Err(crate::Error::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    return Ok(payloads);
+        // nothing after a goodbye matters, and it shouldn't be dropped by the budget
+        if let Some(i) = payloads
+            .iter()
+            .position(|payload| PayloadKind::of(payload) == PayloadKind::Goodbye)
+        {
+            if let Payload::Goodbye(reason) = payloads[i] {
+                self.goodbye = Some(reason);
+            }
+
+            payloads.truncate(i);
+        }
+
+        if let Some(budget) = &mut self.budget {
+            let statistics = &mut self.statistics;
+            let mut exceeded = false;
+
+            payloads.retain(|payload| {
+                if budget.spend_payload() {
+                    true
+                } else {
+                    statistics.payloads_dropped += 1;
+                    statistics.data_bytes_dropped += data_len(payload) as u64;
+                    exceeded = true;
+                    false
                 }
-                Err(e) => return Err(e),
+            });
+
+            if exceeded && budget.action() == BudgetAction::Disconnect {
+                return Err(crate::Error::BudgetExceeded);
             }
         }
+
+        for payload in &payloads {
+            self.statistics.count_received(payload);
+
+            match payload {
+                Payload::Ping { id } => self.control_payloads.push(Payload::Pong { id: *id }),
+                Payload::Pong { id } => match self.ping {
+                    Some((ping_id, sent_at)) if ping_id == *id => {
+                        self.statistics.add_round_trip_time(sent_at.elapsed());
+                        self.ping = None;
+                    }
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+
+        payloads.retain(|payload| PayloadKind::of(payload) != PayloadKind::Ping);
+
+        Ok(payloads)
+    }
+
+    /// Sends a goodbye with `reason` and closes the stream, the connection is useless afterwards.
+    pub fn close(&mut self, reason: DisconnectReason) {
+        let mut payloads = std::mem::replace(&mut self.control_payloads, Vec::new());
+        payloads.push(Payload::Goodbye(reason));
+
+        // the link conditioner could hold the goodbye back
+        if let Err(e) = self.inner.send(payloads, &mut self.statistics) {
+            log::debug!("Failed to say goodbye to {:?}: {:?}", self.actor.id, e);
+        }
+
+        self.inner.close();
+    }
+
+    /// The reason the other end gave for closing, if it did.
+    pub fn goodbye(&self) -> Option<DisconnectReason> {
+        self.goodbye
+    }
+
+    /// How long ago anything was received, pings included.
+    pub fn since_received(&self) -> Duration {
+        self.last_received.elapsed()
+    }
+
+    pub fn since_sent(&self) -> Duration {
+        self.last_sent.elapsed()
+    }
+
+    /// Measures the round trip time with the next send, internal connections are skipped.
+    pub fn ping(&mut self) {
+        if let ConnectionInner::Internal { .. } = self.inner {
+            return;
+        }
+
+        let id = self.next_ping_id;
+        self.next_ping_id += 1;
+
+        self.ping = Some((id, Instant::now()));
+        self.control_payloads.push(Payload::Ping { id });
+    }
+
+    pub fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    /// `None` for internal connections.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        match &self.inner {
+            ConnectionInner::External { addr, .. } => Some(*addr),
+            ConnectionInner::Internal { .. } => None,
+        }
+    }
+
+    pub fn statistics(&self) -> &ConnectionStatistics {
+        &self.statistics
     }
 }
 
@@ -181,19 +435,26 @@ pub struct ConnectionManager {
 
     local_connection_id: ConnectionId,
     local_actor_id: ActorId,
+    /// The connection that assigned our actor id, later connections can't while it's there.
+    local_actor_assigned_by: Option<ActorId>,
+    keep_local_actor_id: bool,
+
+    sessions: Sessions,
+    resume_token: Option<ResumeToken>,
+
+    wire_format: WireFormat,
+    timeout: Option<Duration>,
+    peer_tys: HashSet<ActorTy>,
 }
 
 impl ConnectionManager {
     pub fn new(actor_ty: ActorTy) -> Self {
-        let internal_connection = Connection {
-            inner: ConnectionInner::Internal {
+        let internal_connection = Connection::new(
+            ConnectionInner::Internal {
                 payloads: Vec::new(),
             },
-            actor: Actor {
-                id: ActorId(0),
-                ty: actor_ty,
-            },
-        };
+            Actor::new(ActorId(0), actor_ty),
+        );
 
         let mut connections = HashMap::new();
         connections.insert(ConnectionId(0), internal_connection);
@@ -210,14 +471,24 @@ impl ConnectionManager {
 
             local_connection_id: ConnectionId(0),
             local_actor_id: ActorId(0),
+            local_actor_assigned_by: None,
+            keep_local_actor_id: false,
+
+            sessions: Sessions::new(),
+            resume_token: None,
+
+            wire_format: WireFormat::default(),
+            timeout: None,
+            peer_tys: HashSet::new(),
         }
     }
 
-    pub fn get_targeted_actor_ids(&self, target: &NetworkTarget) -> Vec<ActorId> {
+    pub fn get_targeted_actor_ids(&self, target: &NetworkTarget, rooms: &Rooms) -> Vec<ActorId> {
         match target {
             NetworkTarget::All => self
                 .connections
                 .iter()
+                .filter(|(_, connection)| !self.peer_tys.contains(&connection.actor.ty))
                 .map(|(_, connection)| connection.actor.id)
                 .collect(),
             NetworkTarget::ActorId(actor_id) => {
@@ -233,14 +504,24 @@ impl ConnectionManager {
                 .filter(|(_, connection)| connection.actor.ty == *actor_ty)
                 .map(|(_, connection)| connection.actor.id)
                 .collect(),
+            NetworkTarget::Room(room) => rooms
+                .members(room)
+                .filter(|actor_id| self.get(**actor_id).is_some())
+                .cloned()
+                .collect(),
         }
     }
 
-    pub fn get_targeted_connection_ids(&self, target: &NetworkTarget) -> Vec<ConnectionId> {
+    pub fn get_targeted_connection_ids(
+        &self,
+        target: &NetworkTarget,
+        rooms: &Rooms,
+    ) -> Vec<ConnectionId> {
         match target {
             NetworkTarget::All => self
                 .connections
                 .iter()
+                .filter(|(_, connection)| !self.peer_tys.contains(&connection.actor.ty))
                 .map(|(connection_id, _)| *connection_id)
                 .collect(),
             NetworkTarget::ActorId(actor_id) => {
@@ -256,17 +537,23 @@ impl ConnectionManager {
                 .filter(|(_, connection)| connection.actor.ty == *actor_ty)
                 .map(|(connection_id, _)| *connection_id)
                 .collect(),
+            NetworkTarget::Room(room) => rooms
+                .members(room)
+                .filter_map(|actor_id| self.get_connection_id(actor_id))
+                .cloned()
+                .collect(),
         }
     }
 
     pub fn send(
         &mut self,
         targeted_payloads: Vec<(NetworkTarget, Payload)>,
+        rooms: &Rooms,
     ) -> Vec<ConnectionEvent> {
         let mut connection_id_payloads: HashMap<ConnectionId, Vec<Payload>> = HashMap::new();
 
         for (target, payload) in targeted_payloads {
-            for connection_id in self.get_targeted_connection_ids(&target) {
+            for connection_id in self.get_targeted_connection_ids(&target, rooms) {
                 connection_id_payloads
                     .entry(connection_id)
                     .or_insert(Vec::new())
@@ -276,17 +563,28 @@ impl ConnectionManager {
 
         let mut connection_events = Vec::new();
 
-        for (connection_id, payloads) in connection_id_payloads {
-            if let Some(connection) = self.get_mut(connection_id) {
-                match connection.send(payloads) {
-                    Ok(_) => (),
-                    Err(e) => connection_events.push(ConnectionEvent::Disconnected {
-                        connection_id,
-                        actor: connection.actor.clone(),
-                        cause: e,
-                    }),
+        // connections without new payloads still need to flush what they couldn't write earlier
+        let keepalive = self.timeout.map(|timeout| timeout / 4);
+
+        for (connection_id, connection) in self.connections.iter_mut() {
+            let payloads = connection_id_payloads
+                .remove(connection_id)
+                .unwrap_or_default();
+
+            if let Some(keepalive) = keepalive {
+                if payloads.is_empty() && connection.since_sent() > keepalive {
+                    connection.ping();
                 }
             }
+
+            match connection.send(payloads) {
+                Ok(_) => (),
+                Err(e) => connection_events.push(ConnectionEvent::Disconnected {
+                    connection_id: *connection_id,
+                    actor: connection.actor.clone(),
+                    cause: DisconnectCause::Error(e),
+                }),
+            }
         }
 
         connection_events
@@ -301,7 +599,9 @@ impl ConnectionManager {
             .expect("Local internal connection does for some reason not exist.")
             .clone();
 
-        for (connection_id, connection) in self.connections_mut() {
+        let timeout = self.timeout;
+
+        for (connection_id, connection) in self.connections.iter_mut() {
             match connection.receive() {
                 Ok(payloads) => {
                     let mut connection_messages: Vec<_> = payloads
@@ -319,7 +619,29 @@ impl ConnectionManager {
                     connection_events.push(ConnectionEvent::Disconnected {
                         connection_id: *connection_id,
                         actor: connection.actor.clone(),
-                        cause: e,
+                        cause: DisconnectCause::Error(e),
+                    });
+                    continue;
+                }
+            }
+
+            if let Some(reason) = connection.goodbye() {
+                connection_events.push(ConnectionEvent::Disconnected {
+                    connection_id: *connection_id,
+                    actor: connection.actor.clone(),
+                    cause: DisconnectCause::Remote(reason),
+                });
+                continue;
+            }
+
+            if let Some(timeout) = timeout {
+                if connection.addr().is_some() && connection.since_received() > timeout {
+                    connection.close(DisconnectReason::Timeout);
+
+                    connection_events.push(ConnectionEvent::Disconnected {
+                        connection_id: *connection_id,
+                        actor: connection.actor.clone(),
+                        cause: DisconnectCause::Local(DisconnectReason::Timeout),
                     });
                 }
             }
@@ -348,10 +670,111 @@ impl ConnectionManager {
         get.get_mut(self)
     }
 
+    /// Removes the connection, its session is held until it expires.
     pub fn remove(&mut self, actor_id: ActorId) {
         if let Some(connection_id) = self.connection_ids.remove(&actor_id) {
             self.connections.remove(&connection_id);
+            self.sessions.disconnect(actor_id);
+        }
+    }
+
+    /// Says goodbye to `actor_id` and removes its connection. The session is forgotten
+    /// unless it timed out, returns the event to send if there was a connection.
+    ///
+    /// Internal connections, the local actor's included, can't be disconnected.
+    pub fn disconnect(
+        &mut self,
+        actor_id: ActorId,
+        reason: DisconnectReason,
+    ) -> Option<ConnectionEvent> {
+        let connection_id = *self.connection_ids.get(&actor_id)?;
+
+        if actor_id == self.local_actor_id || self.connections.get(&connection_id)?.addr().is_none()
+        {
+            return None;
         }
+
+        self.connection_ids.remove(&actor_id);
+        let mut connection = self.connections.remove(&connection_id)?;
+
+        connection.close(reason);
+
+        if reason == DisconnectReason::Timeout {
+            self.sessions.disconnect(actor_id);
+        } else {
+            self.sessions.remove(actor_id);
+        }
+
+        Some(ConnectionEvent::Disconnected {
+            actor: connection.actor,
+            connection_id,
+            cause: DisconnectCause::Local(reason),
+        })
+    }
+
+    /// Disconnects every external connection with `reason`.
+    pub fn shutdown(&mut self, reason: DisconnectReason) -> Vec<ConnectionEvent> {
+        let actor_ids: Vec<_> = self
+            .connections
+            .values()
+            .filter(|connection| connection.addr().is_some())
+            .map(|connection| connection.actor.id)
+            .collect();
+
+        actor_ids
+            .into_iter()
+            .filter_map(|actor_id| self.disconnect(actor_id, reason))
+            .collect()
+    }
+
+    pub fn sessions(&self) -> &Sessions {
+        &self.sessions
+    }
+
+    pub fn expire_sessions(&mut self, grace_period: Duration) -> Vec<ActorId> {
+        self.sessions.expire(grace_period)
+    }
+
+    /// Payload data has to be encoded with this, it's the same for every connection.
+    pub fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+
+    pub fn set_wire_format(&mut self, wire_format: WireFormat) {
+        self.wire_format = wire_format;
+    }
+
+    /// Connections of these types are only targeted by id or type, see [`NetworkSettings::peer_tys`].
+    pub fn set_peer_tys(&mut self, peer_tys: impl IntoIterator<Item = ActorTy>) {
+        self.peer_tys = peer_tys.into_iter().collect();
+    }
+
+    /// Connections that don't receive anything for this long are closed, and everyone is
+    /// pinged often enough to not time out on the other end.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// The token handed out by the last connection that assigned us an actor id.
+    pub fn resume_token(&self) -> Option<ResumeToken> {
+        self.resume_token
+    }
+
+    /// Resumes with `resume_token` the next time a connection assigns us an actor id.
+    pub fn set_resume_token(&mut self, resume_token: Option<ResumeToken>) {
+        self.resume_token = resume_token;
+    }
+
+    /// Reserves an actor id for whoever connects with `resume_token` before the session expires.
+    pub fn reserve_actor(
+        &mut self,
+        resume_token: ResumeToken,
+        player_id: Option<PlayerId>,
+    ) -> ActorId {
+        let actor_id = self.generate_actor_id();
+        self.sessions.hold(resume_token, actor_id, player_id);
+
+        actor_id
     }
 
     pub fn get_connection_id(&self, actor_id: &ActorId) -> Option<&ConnectionId> {
@@ -370,11 +793,34 @@ impl ConnectionManager {
     }
 
     pub fn generate_actor_id(&mut self) -> ActorId {
+        // ids assigned by the other end of a connection can be anywhere
+        while self.connection_ids.contains_key(&self.next_actor_id) {
+            self.next_actor_id.0 += 1;
+        }
+
         let id = self.next_actor_id;
         self.next_actor_id.0 += 1;
         id
     }
 
+    /// Keeps the local actor id, new connections get ids from this manager instead of adopting
+    /// the ones the other end assigns. Listeners always do.
+    pub fn set_keep_local_actor_id(&mut self, keep_local_actor_id: bool) {
+        self.keep_local_actor_id = keep_local_actor_id;
+    }
+
+    pub fn keep_local_actor_id(&self) -> bool {
+        self.keep_local_actor_id
+    }
+
+    fn local_actor_assigned(&self) -> bool {
+        self.keep_local_actor_id
+            || self
+                .local_actor_assigned_by
+                .map(|actor_id| self.connection_ids.contains_key(&actor_id))
+                .unwrap_or(false)
+    }
+
     pub fn set_local_actor_id(&mut self, actor_id: ActorId) {
         let connection_id = self
             .connection_ids
@@ -391,76 +837,454 @@ impl ConnectionManager {
         self.local_actor_id = actor_id;
     }
 
+    pub fn set_local_player_id(&mut self, player_id: Option<PlayerId>) {
+        let local_actor_id = self.local_actor_id;
+
+        self.get_mut(local_actor_id)
+            .expect("Welp, apparently an interal local connection does not exist")
+            .actor
+            .player_id = player_id;
+    }
+
+    /// An empty manager to handshake with on another thread, resuming with our resume token and
+    /// sharing our sessions.
+    pub fn detached(&self) -> ConnectionManager {
+        let local_actor = self
+            .get_local_actor()
+            .expect("Local internal connection does for some reason not exist.");
+
+        let mut connection_manager = ConnectionManager::new(local_actor.ty());
+        connection_manager.sessions = self.sessions.clone();
+        connection_manager.resume_token = self.resume_token;
+        connection_manager.wire_format = self.wire_format;
+        connection_manager.timeout = self.timeout;
+
+        connection_manager
+    }
+
+    /// Moves the connections of a [`detached`](Self::detached) manager into this one, along with
+    /// the ids it was assigned. If we [keep ours](Self::set_keep_local_actor_id), its actors
+    /// get new ids from us instead, unless it kept its own too and handed out ids we generated,
+    /// like listeners do. Returns `event` with the ids it has here.
+    pub fn attach(
+        &mut self,
+        mut connection_manager: ConnectionManager,
+        mut event: ConnectionEvent,
+    ) -> ConnectionEvent {
+        if !self.keep_local_actor_id {
+            let local_actor = connection_manager
+                .get_local_actor()
+                .expect("Local internal connection does for some reason not exist.")
+                .clone();
+
+            self.set_local_actor_id(local_actor.id());
+            self.set_local_player_id(local_actor.player_id());
+            self.local_actor_assigned_by = connection_manager.local_actor_assigned_by;
+            self.resume_token = connection_manager.resume_token;
+            self.wire_format = connection_manager.wire_format;
+        } else if connection_manager.wire_format != self.wire_format {
+            log::warn!(
+                "Attached a connection using {:?} instead of {:?}",
+                connection_manager.wire_format,
+                self.wire_format
+            );
+        }
+
+        connection_manager
+            .connections
+            .remove(&connection_manager.local_connection_id);
+
+        for (old_connection_id, mut connection) in connection_manager.connections.drain() {
+            let connection_id = self.generate_connection_id();
+
+            if self.keep_local_actor_id && !connection_manager.keep_local_actor_id {
+                connection.actor.id = self.generate_actor_id();
+            }
+
+            if let ConnectionEvent::Connected {
+                actor,
+                connection_id: event_connection_id,
+                ..
+            } = &mut event
+            {
+                if *event_connection_id == old_connection_id {
+                    *event_connection_id = connection_id;
+                    *actor = connection.actor.clone();
+                }
+            }
+
+            self.connection_ids
+                .insert(connection.actor.id, connection_id);
+            self.connections.insert(connection_id, connection);
+        }
+
+        event
+    }
+
     pub fn add_connection(
         &mut self,
-        mut stream: TcpStream,
+        stream: TcpStream,
         actor_ty: ActorTy,
-        send_handshake: Handshake,
-    ) -> ConnectionEvent {
-        stream.set_nodelay(true).unwrap();
-        stream.set_nonblocking(false).unwrap();
+        mut send_handshake: Handshake,
+        network_settings: &NetworkSettings,
+        account_store: Option<&mut AccountStore>,
+    ) -> Result<ConnectionEvent, crate::Error> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(false)?;
+
+        let addr = stream.peer_addr()?;
+        let mut stream = FramedStream::new(stream);
+
+        let key_exchange = match network_settings.encryption {
+            Encryption::Disabled => None,
+            Encryption::Enabled | Encryption::Required => Some(KeyExchange::new()),
+        };
+
+        send_handshake.public_key = key_exchange.as_ref().map(KeyExchange::public_key);
+        send_handshake.wire_format = self.wire_format;
+        let (issued_token, presented_token) = match send_handshake.actor_ids {
+            HandshakeActorIds::Override { .. } => (
+                network_settings
+                    .resume_grace_period
+                    .map(|_| ResumeToken::generate()),
+                None,
+            ),
+            HandshakeActorIds::None if !self.local_actor_assigned() => (None, self.resume_token),
+            HandshakeActorIds::None => (None, None),
+        };
+        send_handshake.resume_token = issued_token.is_some() || presented_token.is_some();
+
+        let sent_handshake = serde_cbor::to_vec(&send_handshake)?;
+        stream.write_frame(&sent_handshake)?;
+
+        let received_handshake = stream.read_frame_blocking()?;
+        let handshake: Handshake = serde_cbor::from_slice(&received_handshake)?;
+
+        if let Some(reason) = handshake.goodbye {
+            return Err(crate::Error::Goodbye(reason));
+        }
+
+        // both ends check, so the other one notices without being told
+        if handshake.version != PROTOCOL_VERSION {
+            return Err(crate::Error::VersionMismatch {
+                local: PROTOCOL_VERSION,
+                remote: handshake.version,
+            });
+        }
 
-        let bytes = serde_cbor::to_vec(&send_handshake).unwrap();
-        let len = (bytes.len() as u64).to_be_bytes();
-        stream.write(&len).unwrap();
-        stream.write(&bytes).unwrap();
+        match handshake.tick_duration {
+            Some(remote) if remote != network_settings.tick_duration => {
+                return Err(crate::Error::TickMismatch {
+                    local: network_settings.tick_duration,
+                    remote,
+                });
+            }
+            _ => (),
+        }
+
+        let mut cipher = match (key_exchange, handshake.public_key) {
+            (Some(key_exchange), Some(public_key)) => {
+                Some(key_exchange.finish(public_key, &sent_handshake, &received_handshake))
+            }
+            _ if network_settings.encryption == Encryption::Required => {
+                return Err(crate::Error::EncryptionRequired);
+            }
+            _ => None,
+        };
+
+        // a tampered handshake leaves the two ends with different keys, this finds out right away
+        if cipher.is_some() {
+            write_sealed(&mut stream, &mut cipher, &PROTOCOL_VERSION)?;
+            let _: u32 = read_sealed(&mut stream, &mut cipher)?;
+        }
+
+        // someone in the middle would have made both key exchanges, the signature only fits the
+        // handshakes the other end saw
+        if let (Some(identity), true) = (&network_settings.identity, cipher.is_some()) {
+            let signature = identity.sign(&sent_handshake, &received_handshake);
+            write_sealed(&mut stream, &mut cipher, &signature)?;
+        }
 
-        let mut buf = [0u8; 8];
-        stream.read(&mut buf).unwrap();
-        let len = u64::from_be_bytes(buf) as usize;
+        let signature: Option<Vec<u8>> = if handshake.identified && cipher.is_some() {
+            Some(read_sealed(&mut stream, &mut cipher)?)
+        } else {
+            None
+        };
 
-        let mut buf = vec![0u8; len];
-        stream.read(&mut buf).unwrap();
-        let handshake: Handshake = serde_cbor::from_slice(&buf).unwrap();
+        if let Some(server_key) = &network_settings.server_key {
+            match signature {
+                Some(signature)
+                    if server_key.verify(&sent_handshake, &received_handshake, &signature) => {}
+                _ => return Err(crate::Error::IdentityMismatch),
+            }
+        }
+
+        // peer types are only granted with the secret, which never goes out in the clear
+        if send_handshake.actor_ty.is_some() {
+            match (&network_settings.peer_secret, cipher.is_some()) {
+                (Some(secret), true) => write_sealed(&mut stream, &mut cipher, secret)?,
+                _ => return Err(crate::Error::PeerRejected),
+            }
+        }
 
-        let actor_id = match handshake {
-            Handshake::Override {
+        let actor_ty = match handshake.actor_ty {
+            Some(claimed) => {
+                if cipher.is_none() {
+                    return Err(crate::Error::PeerRejected);
+                }
+
+                let secret: String = read_sealed(&mut stream, &mut cipher)?;
+
+                let accepted = network_settings.peer_tys.contains(&claimed)
+                    && network_settings
+                        .peer_secret
+                        .as_ref()
+                        .map(|expected| constant_time_eq(secret.as_bytes(), expected.as_bytes()))
+                        .unwrap_or(false);
+
+                if !accepted {
+                    return Err(crate::Error::PeerRejected);
+                }
+
+                claimed
+            }
+            None => actor_ty,
+        };
+
+        if let Some(credentials) = &network_settings.credentials {
+            if cipher.is_none() {
+                if handshake.login_required {
+                    return Err(crate::Error::EncryptionRequired);
+                }
+
+                log::warn!("Logging in over an unencrypted connection");
+            }
+
+            write_sealed(&mut stream, &mut cipher, credentials)?;
+
+            match read_sealed(&mut stream, &mut cipher)? {
+                LoginResponse::Accepted { player_id } => self.set_local_player_id(Some(player_id)),
+                LoginResponse::Rejected(rejection) => {
+                    return Err(crate::Error::LoginRejected(rejection))
+                }
+            }
+        } else if handshake.login_required {
+            return Err(crate::Error::LoginRejected(LoginRejection::LoginRequired));
+        }
+
+        let player_id = if handshake.login {
+            if cipher.is_none() && network_settings.require_login {
+                return Err(crate::Error::EncryptionRequired);
+            }
+
+            let credentials: Credentials = read_sealed(&mut stream, &mut cipher)?;
+
+            let login = match account_store {
+                Some(account_store) => account_store.login(&credentials),
+                None => Err(LoginRejection::InvalidCredentials),
+            };
+
+            match login {
+                Ok(player_id) => {
+                    write_sealed(
+                        &mut stream,
+                        &mut cipher,
+                        &LoginResponse::Accepted { player_id },
+                    )?;
+
+                    Some(player_id)
+                }
+                Err(rejection) => {
+                    write_sealed(
+                        &mut stream,
+                        &mut cipher,
+                        &LoginResponse::Rejected(rejection),
+                    )?;
+
+                    return Err(crate::Error::LoginRejected(rejection));
+                }
+            }
+        } else if network_settings.require_login {
+            return Err(crate::Error::LoginRejected(LoginRejection::LoginRequired));
+        } else {
+            None
+        };
+
+        // resume tokens never go out in the clear, anyone reading them could take over the actor
+        let (issued_token, presented_token) = if cipher.is_some() {
+            (issued_token, presented_token)
+        } else {
+            (None, None)
+        };
+        let offered_token = handshake.resume_token && cipher.is_some();
+
+        let mut resumed = false;
+
+        let actor_id = match handshake.actor_ids {
+            // another connection already decided who we are, these ids only make sense over there
+            HandshakeActorIds::Override { .. } if self.local_actor_assigned() => {
+                if handshake.wire_format != self.wire_format {
+                    return Err(crate::Error::WireFormatMismatch);
+                }
+
+                if offered_token {
+                    let _: ResumeToken = read_sealed(&mut stream, &mut cipher)?;
+                }
+
+                self.generate_actor_id()
+            }
+            HandshakeActorIds::Override {
                 sender_actor_id,
                 receiver_actor_id,
             } => {
-                self.set_local_actor_id(receiver_actor_id);
+                let resumed_actor_id = match presented_token {
+                    Some(resume_token) => {
+                        write_sealed(&mut stream, &mut cipher, &resume_token)?;
+                        read_sealed(&mut stream, &mut cipher)?
+                    }
+                    None => None,
+                };
+
+                self.resume_token = if offered_token {
+                    Some(read_sealed(&mut stream, &mut cipher)?)
+                } else {
+                    None
+                };
+
+                resumed = resumed_actor_id.is_some();
+                self.set_local_actor_id(resumed_actor_id.unwrap_or(receiver_actor_id));
+                self.wire_format = handshake.wire_format;
+                self.local_actor_assigned_by = Some(sender_actor_id);
                 sender_actor_id
             }
-            Handshake::None => match send_handshake {
-                Handshake::Override {
+            HandshakeActorIds::None => match send_handshake.actor_ids {
+                HandshakeActorIds::Override {
                     receiver_actor_id, ..
-                } => receiver_actor_id,
-                Handshake::None => self.generate_actor_id(),
+                } => {
+                    let resumed_actor_id = if offered_token {
+                        let resume_token: ResumeToken = read_sealed(&mut stream, &mut cipher)?;
+
+                        let resumed_actor_id = match issued_token {
+                            Some(_) => self.sessions.resume(&resume_token, player_id),
+                            None => None,
+                        };
+
+                        write_sealed(&mut stream, &mut cipher, &resumed_actor_id)?;
+                        resumed_actor_id
+                    } else {
+                        None
+                    };
+
+                    resumed = resumed_actor_id.is_some();
+                    resumed_actor_id.unwrap_or(receiver_actor_id)
+                }
+                HandshakeActorIds::None => self.generate_actor_id(),
             },
         };
 
-        let actor = Actor::new(actor_id, actor_ty);
+        if let Some(resume_token) = issued_token {
+            write_sealed(&mut stream, &mut cipher, &resume_token)?;
+            self.sessions.insert(resume_token, actor_id, player_id);
+        }
 
-        stream.set_nonblocking(true).unwrap();
+        let compression = if handshake.compression {
+            network_settings.compression.clone()
+        } else {
+            None
+        };
+
+        let actor = Actor::new(actor_id, actor_ty).with_player_id(player_id);
+
+        stream.stream().set_nonblocking(true)?;
 
         let connection_id = self.generate_connection_id();
-        let connection = Connection {
-            inner: ConnectionInner::External {
-                addr: stream.peer_addr().unwrap(),
+        let connection = Connection::new(
+            ConnectionInner::External {
+                addr,
                 stream,
-                next_len: None,
+                compression,
+                cipher,
+                wire_format: self.wire_format,
             },
-            actor: actor.clone(),
-        };
+            actor.clone(),
+        )
+        .with_link(
+            network_settings
+                .link_conditioner
+                .clone()
+                .map(|conditioner| ConditionedLink::new(conditioner, connection_id)),
+        )
+        // links between servers carry handoffs that can't be dropped
+        .with_budget(if network_settings.peer_tys.contains(&actor_ty) {
+            None
+        } else {
+            network_settings.inbound_budget.clone()
+        });
 
         self.connections.insert(connection_id, connection);
         self.connection_ids.insert(actor.id(), connection_id);
 
-        ConnectionEvent::Connected {
+        Ok(ConnectionEvent::Connected {
             actor,
             connection_id,
-        }
+            player_id,
+            resumed,
+        })
+    }
+}
+
+/// Writes a frame during the handshake, before compression is set up.
+fn write_sealed<T: Serialize>(
+    stream: &mut FramedStream,
+    cipher: &mut Option<Cipher>,
+    value: &T,
+) -> Result<(), crate::Error> {
+    let mut bytes = serde_cbor::to_vec(value)?;
+
+    if let Some(cipher) = cipher {
+        bytes = cipher.encrypt(&bytes)?;
+    }
+
+    stream.write_frame(&bytes)
+}
+
+fn read_sealed<T: DeserializeOwned>(
+    stream: &mut FramedStream,
+    cipher: &mut Option<Cipher>,
+) -> Result<T, crate::Error> {
+    let mut bytes = stream.read_frame_blocking()?;
+
+    if let Some(cipher) = cipher {
+        bytes = cipher.decrypt(&bytes)?;
     }
+
+    Ok(serde_cbor::from_slice(&bytes)?)
 }
 
 pub fn disconnect_handler_system(
     mut connection_manager: ResMut<ConnectionManager>,
+    mut spawn_manager: ResMut<SpawnManager>,
+    mut rooms: ResMut<Rooms>,
+    network_settings: Res<NetworkSettings>,
     mut event_reader: Local<EventReader<ConnectionEvent>>,
     events: Res<Events<ConnectionEvent>>,
 ) {
     for event in event_reader.iter(&events) {
         if let ConnectionEvent::Disconnected { actor, .. } = event {
             connection_manager.remove(actor.id());
+
+            // actors whose session is held for resume keep their rooms until it expires
+            if !connection_manager.sessions().is_held(actor.id()) {
+                spawn_manager.forget(actor.id());
+                rooms.leave_all(actor.id());
+            }
+        }
+    }
+
+    if let Some(grace_period) = network_settings.resume_grace_period {
+        for actor_id in connection_manager.expire_sessions(grace_period) {
+            spawn_manager.forget(actor_id);
+            rooms.leave_all(actor_id);
         }
     }
 }
@@ -0,0 +1,292 @@
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use ed25519_dalek::{Keypair, SecretKey, Signer, Verifier};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{fmt, fs, path::Path, str::FromStr, sync::Arc};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+/// Keys are agreed on with a key exchange that on its own checks nobody's identity. That keeps
+/// what's sent from passive observers, but anyone who can intercept the connection can sit in
+/// the middle and read everything, credentials included, unless the client pins the server's
+/// [`IdentityKey`] with [`NetworkSettings::server_key`]. The handshakes are bound into the keys,
+/// so they can't be changed on the way without the connection failing.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Encryption {
+    Disabled,
+    /// Encrypts the connection if the other end supports it. Someone in the middle can still
+    /// strip the public keys from the handshakes and leave it unencrypted.
+    Enabled,
+    /// Refuses connections that can't be encrypted.
+    Required,
+}
+
+/// A server's long-term signing key. It signs both handshakes, which carry the keys of the
+/// exchange, so an end pinning its [`IdentityKey`] knows nobody else took part in it.
+#[derive(Clone)]
+pub struct Identity {
+    keypair: Arc<Keypair>,
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        Self {
+            keypair: Arc::new(Keypair::generate(&mut OsRng)),
+        }
+    }
+
+    pub fn from_bytes(secret: &[u8]) -> Result<Self, crate::Error> {
+        let secret = SecretKey::from_bytes(secret).map_err(|_| crate::Error::InvalidIdentity)?;
+        let public = (&secret).into();
+
+        Ok(Self {
+            keypair: Arc::new(Keypair { secret, public }),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.keypair.secret.to_bytes()
+    }
+
+    /// Reads the identity in `path`, or generates one and writes it there if there's none yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+        let path = path.as_ref();
+
+        if path.exists() {
+            Self::from_bytes(&fs::read(path)?)
+        } else {
+            let identity = Self::generate();
+            fs::write(path, identity.to_bytes())?;
+            Ok(identity)
+        }
+    }
+
+    pub fn public_key(&self) -> IdentityKey {
+        IdentityKey(self.keypair.public.to_bytes())
+    }
+
+    /// `sent_handshake` and `received_handshake` as they went over the wire.
+    pub fn sign(&self, sent_handshake: &[u8], received_handshake: &[u8]) -> Vec<u8> {
+        self.keypair
+            .sign(&transcript(sent_handshake, received_handshake))
+            .to_bytes()
+            .to_vec()
+    }
+}
+
+/// The public half of an [`Identity`], written as 64 hex digits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct IdentityKey(pub [u8; 32]);
+
+impl IdentityKey {
+    /// Whether `signature` is [`Identity::sign`] of the other end, with the handshakes as we saw
+    /// them.
+    pub fn verify(
+        &self,
+        sent_handshake: &[u8],
+        received_handshake: &[u8],
+        signature: &[u8],
+    ) -> bool {
+        let public_key = match ed25519_dalek::PublicKey::from_bytes(&self.0) {
+            Ok(public_key) => public_key,
+            Err(_) => return false,
+        };
+        let signature = match ed25519_dalek::Signature::from_bytes(signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+
+        public_key
+            .verify(&transcript(received_handshake, sent_handshake), &signature)
+            .is_ok()
+    }
+}
+
+impl fmt::Display for IdentityKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for IdentityKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("expected 64 hex digits, got {}", s);
+
+        if s.len() != 64 || !s.is_ascii() {
+            return Err(error());
+        }
+
+        let mut key = [0u8; 32];
+
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| error())?;
+        }
+
+        Ok(IdentityKey(key))
+    }
+}
+
+/// What the signer signs, its own handshake first.
+fn transcript(signer_handshake: &[u8], verifier_handshake: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"mmo-tech identity");
+    hasher.update(Sha256::digest(signer_handshake));
+    hasher.update(Sha256::digest(verifier_handshake));
+    hasher.finalize().to_vec()
+}
+
+pub struct KeyExchange {
+    secret: EphemeralSecret,
+    public_key: PublicKey,
+}
+
+impl KeyExchange {
+    pub fn new() -> Self {
+        let secret = EphemeralSecret::new(OsRng);
+        let public_key = PublicKey::from(&secret);
+
+        Self { secret, public_key }
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        *self.public_key.as_bytes()
+    }
+
+    /// `sent_handshake` and `received_handshake` are the encoded handshakes as they went over
+    /// the wire, if either was changed on the way the two ends end up with different keys.
+    pub fn finish(
+        self,
+        peer_public_key: [u8; 32],
+        sent_handshake: &[u8],
+        received_handshake: &[u8],
+    ) -> Cipher {
+        let public_key = self.public_key();
+        let shared_secret = self
+            .secret
+            .diffie_hellman(&PublicKey::from(peer_public_key));
+
+        let sent_handshake = Sha256::digest(sent_handshake);
+        let received_handshake = Sha256::digest(received_handshake);
+
+        // each direction gets its own key, so both ends derive the same pair without
+        // having to agree on who initiated the connection
+        let derive_key = |sender: &[u8; 32],
+                          receiver: &[u8; 32],
+                          sender_handshake: &[u8],
+                          receiver_handshake: &[u8]| {
+            let mut hasher = Sha256::new();
+            hasher.update(b"mmo-tech session key");
+            hasher.update(shared_secret.as_bytes());
+            hasher.update(sender);
+            hasher.update(receiver);
+            hasher.update(sender_handshake);
+            hasher.update(receiver_handshake);
+            ChaCha20Poly1305::new(Key::from_slice(&hasher.finalize()))
+        };
+
+        Cipher {
+            send: derive_key(
+                &public_key,
+                &peer_public_key,
+                &sent_handshake,
+                &received_handshake,
+            ),
+            receive: derive_key(
+                &peer_public_key,
+                &public_key,
+                &received_handshake,
+                &sent_handshake,
+            ),
+            send_nonce: 0,
+            receive_nonce: 0,
+        }
+    }
+}
+
+/// Authenticated encryption of frames, nonces are frame counters so dropped, replayed or
+/// reordered frames fail to decrypt just like modified ones.
+pub struct Cipher {
+    send: ChaCha20Poly1305,
+    receive: ChaCha20Poly1305,
+    send_nonce: u64,
+    receive_nonce: u64,
+}
+
+impl Cipher {
+    fn nonce(counter: u64) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        nonce
+    }
+
+    pub fn encrypt(&mut self, bytes: &[u8]) -> Result<Vec<u8>, crate::Error> {
+        let nonce = Self::nonce(self.send_nonce);
+        self.send_nonce += 1;
+
+        self.send
+            .encrypt(Nonce::from_slice(&nonce), bytes)
+            .map_err(|_| crate::Error::Encryption)
+    }
+
+    /// Moves past a received frame without decrypting it.
+    pub fn skip(&mut self) {
+        self.receive_nonce += 1;
+    }
+
+    pub fn decrypt(&mut self, bytes: &[u8]) -> Result<Vec<u8>, crate::Error> {
+        let nonce = Self::nonce(self.receive_nonce);
+        self.receive_nonce += 1;
+
+        self.receive
+            .decrypt(Nonce::from_slice(&nonce), bytes)
+            .map_err(|_| crate::Error::TamperedFrame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_only_fit_the_handshakes_they_were_made_for() {
+        let identity = Identity::generate();
+        let signature = identity.sign(b"server", b"client");
+
+        assert!(identity
+            .public_key()
+            .verify(b"client", b"server", &signature));
+        assert!(!identity
+            .public_key()
+            .verify(b"client", b"someone else", &signature));
+        assert!(!Identity::generate()
+            .public_key()
+            .verify(b"client", b"server", &signature));
+    }
+
+    #[test]
+    fn identities_survive_being_stored() {
+        let identity = Identity::generate();
+        let restored = Identity::from_bytes(&identity.to_bytes()).unwrap();
+
+        assert_eq!(restored.public_key(), identity.public_key());
+        assert!(Identity::from_bytes(&[0; 7]).is_err());
+    }
+
+    #[test]
+    fn identity_keys_are_written_as_hex() {
+        let key = Identity::generate().public_key();
+
+        assert_eq!(key.to_string().len(), 64);
+        assert_eq!(key.to_string().parse(), Ok(key));
+        assert!("not a key".parse::<IdentityKey>().is_err());
+    }
+}
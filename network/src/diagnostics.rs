@@ -0,0 +1,360 @@
+use crate::*;
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::*,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Per second rates over the last sample interval.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionRates {
+    pub bytes_sent: f64,
+    pub bytes_received: f64,
+    pub payloads_sent: f64,
+    pub payloads_received: f64,
+    /// Payloads dropped for going over the [`InboundBudget`].
+    pub payloads_dropped: f64,
+    pub payload_kinds: HashMap<PayloadKind, PayloadKindRates>,
+}
+
+/// Per second rates of one kind of payload, bytes only count payload data.
+#[derive(Clone, Debug, Default)]
+pub struct PayloadKindRates {
+    pub sent: f64,
+    pub received: f64,
+    pub data_bytes_sent: f64,
+    pub data_bytes_received: f64,
+}
+
+impl PayloadKindRates {
+    fn between(counts: &PayloadCounts, previous: &PayloadCounts, seconds: f64) -> Self {
+        Self {
+            sent: (counts.sent - previous.sent) as f64 / seconds,
+            received: (counts.received - previous.received) as f64 / seconds,
+            data_bytes_sent: (counts.data_bytes_sent - previous.data_bytes_sent) as f64 / seconds,
+            data_bytes_received: (counts.data_bytes_received - previous.data_bytes_received) as f64
+                / seconds,
+        }
+    }
+}
+
+pub struct NetworkDiagnostics {
+    sample_interval: Duration,
+    log_interval: Option<Duration>,
+    last_sample: Instant,
+    last_log: Instant,
+    previous: HashMap<ConnectionId, ConnectionStatistics>,
+    rates: HashMap<ConnectionId, ConnectionRates>,
+}
+
+impl NetworkDiagnostics {
+    pub fn new(sample_interval: Duration, log_interval: Option<Duration>) -> Self {
+        Self {
+            sample_interval,
+            log_interval,
+            last_sample: Instant::now(),
+            last_log: Instant::now(),
+            previous: HashMap::new(),
+            rates: HashMap::new(),
+        }
+    }
+
+    pub fn rates(&self, connection_id: ConnectionId) -> Option<&ConnectionRates> {
+        self.rates.get(&connection_id)
+    }
+
+    /// Rates of `kind` summed over every connection.
+    pub fn payload_kind_rates(&self, kind: PayloadKind) -> PayloadKindRates {
+        let mut total = PayloadKindRates::default();
+
+        for kind_rates in self
+            .rates
+            .values()
+            .filter_map(|rates| rates.payload_kinds.get(&kind))
+        {
+            total.sent += kind_rates.sent;
+            total.received += kind_rates.received;
+            total.data_bytes_sent += kind_rates.data_bytes_sent;
+            total.data_bytes_received += kind_rates.data_bytes_received;
+        }
+
+        total
+    }
+}
+
+/// Samples [`ConnectionStatistics`] into Bevy's [`Diagnostics`], pings connections for round trip
+/// times and optionally logs a summary table.
+pub struct NetworkDiagnosticsPlugin {
+    /// How often rates are sampled and connections are pinged.
+    pub sample_interval: Duration,
+    pub log_interval: Option<Duration>,
+}
+
+impl Default for NetworkDiagnosticsPlugin {
+    fn default() -> Self {
+        Self {
+            sample_interval: Duration::from_secs(1),
+            log_interval: None,
+        }
+    }
+}
+
+impl NetworkDiagnosticsPlugin {
+    pub const BYTES_SENT: DiagnosticId =
+        DiagnosticId::from_u128(288473527066138795410425924150451813169);
+    pub const BYTES_RECEIVED: DiagnosticId =
+        DiagnosticId::from_u128(118096215335498102961413285806407128370);
+    pub const PAYLOADS_SENT: DiagnosticId =
+        DiagnosticId::from_u128(36257963787539046116702411409815722275);
+    pub const PAYLOADS_RECEIVED: DiagnosticId =
+        DiagnosticId::from_u128(198632432158311069618226830178460683476);
+    pub const CONNECTIONS: DiagnosticId =
+        DiagnosticId::from_u128(245937306540227911315282468126416092213);
+    pub const ROUND_TRIP_TIME: DiagnosticId =
+        DiagnosticId::from_u128(103651883096364745932539564386412655222);
+    pub const SPAWN_BACKLOG: DiagnosticId =
+        DiagnosticId::from_u128(309415126451573396287342637520416346487);
+    pub const PAYLOADS_DROPPED: DiagnosticId =
+        DiagnosticId::from_u128(57293419083246601752839028165120733911);
+
+    pub fn with_log_interval(mut self, log_interval: Duration) -> Self {
+        self.log_interval = Some(log_interval);
+        self
+    }
+
+    pub fn setup_system(mut diagnostics: ResMut<Diagnostics>) {
+        diagnostics.add(Diagnostic::new(Self::BYTES_SENT, "network_bytes_sent", 20));
+        diagnostics.add(Diagnostic::new(
+            Self::BYTES_RECEIVED,
+            "network_bytes_received",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::PAYLOADS_SENT,
+            "network_payloads_sent",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::PAYLOADS_RECEIVED,
+            "network_payloads_received",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::CONNECTIONS,
+            "network_connections",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::ROUND_TRIP_TIME,
+            "network_round_trip_time_ms",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::SPAWN_BACKLOG,
+            "network_spawn_backlog",
+            20,
+        ));
+        diagnostics.add(Diagnostic::new(
+            Self::PAYLOADS_DROPPED,
+            "network_payloads_dropped",
+            20,
+        ));
+    }
+}
+
+impl Plugin for NetworkDiagnosticsPlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        if !app_builder.resources().contains::<Diagnostics>() {
+            app_builder.init_resource::<Diagnostics>();
+        }
+
+        app_builder.add_resource(NetworkDiagnostics::new(
+            self.sample_interval,
+            self.log_interval,
+        ));
+
+        app_builder.add_startup_system(Self::setup_system);
+        app_builder.add_system_to_stage(bevy::app::stage::POST_UPDATE, network_diagnostics_system);
+    }
+}
+
+pub fn network_diagnostics_system(
+    mut network_diagnostics: ResMut<NetworkDiagnostics>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    spawn_manager: Res<SpawnManager>,
+    rooms: Res<Rooms>,
+    network_type_registry: Res<NetworkTypeRegistry>,
+) {
+    let elapsed = network_diagnostics.last_sample.elapsed();
+
+    if elapsed < network_diagnostics.sample_interval {
+        return;
+    }
+
+    network_diagnostics.last_sample = Instant::now();
+
+    let seconds = elapsed.as_secs_f64();
+    let mut total = ConnectionRates::default();
+    let mut round_trip_times = Vec::new();
+    let mut spawn_backlog = 0;
+    let mut rates = HashMap::new();
+
+    for (connection_id, connection) in connection_manager.connections_mut() {
+        let statistics = connection.statistics();
+        let previous = network_diagnostics
+            .previous
+            .remove(connection_id)
+            .unwrap_or_default();
+
+        let connection_rates = ConnectionRates {
+            bytes_sent: (statistics.bytes_sent - previous.bytes_sent) as f64 / seconds,
+            bytes_received: (statistics.bytes_received - previous.bytes_received) as f64 / seconds,
+            payloads_sent: (statistics.payloads_sent - previous.payloads_sent) as f64 / seconds,
+            payloads_received: (statistics.payloads_received - previous.payloads_received) as f64
+                / seconds,
+            payloads_dropped: (statistics.payloads_dropped - previous.payloads_dropped) as f64
+                / seconds,
+            payload_kinds: statistics
+                .payload_kinds
+                .iter()
+                .map(|(kind, counts)| {
+                    let previous = previous
+                        .payload_kinds
+                        .get(kind)
+                        .cloned()
+                        .unwrap_or_default();
+                    (*kind, PayloadKindRates::between(counts, &previous, seconds))
+                })
+                .collect(),
+        };
+
+        total.bytes_sent += connection_rates.bytes_sent;
+        total.bytes_received += connection_rates.bytes_received;
+        total.payloads_sent += connection_rates.payloads_sent;
+        total.payloads_received += connection_rates.payloads_received;
+        total.payloads_dropped += connection_rates.payloads_dropped;
+
+        if let Some(round_trip_time) = statistics.round_trip_time {
+            round_trip_times.push(round_trip_time);
+        }
+
+        spawn_backlog += spawn_manager
+            .get_not_spawned(connection.actor().id())
+            .iter()
+            .filter(|(_, target, _)| connection.actor().targeted_by(target, &*rooms))
+            .count();
+
+        rates.insert(*connection_id, connection_rates);
+
+        connection.ping();
+    }
+
+    network_diagnostics.previous = connection_manager
+        .connections()
+        .map(|(connection_id, connection)| (*connection_id, connection.statistics().clone()))
+        .collect();
+    network_diagnostics.rates = rates;
+
+    diagnostics.add_measurement(NetworkDiagnosticsPlugin::BYTES_SENT, total.bytes_sent);
+    diagnostics.add_measurement(
+        NetworkDiagnosticsPlugin::BYTES_RECEIVED,
+        total.bytes_received,
+    );
+    diagnostics.add_measurement(NetworkDiagnosticsPlugin::PAYLOADS_SENT, total.payloads_sent);
+    diagnostics.add_measurement(
+        NetworkDiagnosticsPlugin::PAYLOADS_RECEIVED,
+        total.payloads_received,
+    );
+    diagnostics.add_measurement(
+        NetworkDiagnosticsPlugin::PAYLOADS_DROPPED,
+        total.payloads_dropped,
+    );
+    diagnostics.add_measurement(
+        NetworkDiagnosticsPlugin::CONNECTIONS,
+        connection_manager.connections().count() as f64,
+    );
+    diagnostics.add_measurement(
+        NetworkDiagnosticsPlugin::SPAWN_BACKLOG,
+        spawn_backlog as f64,
+    );
+
+    if !round_trip_times.is_empty() {
+        let sum: Duration = round_trip_times.iter().sum();
+        let average = sum / round_trip_times.len() as u32;
+
+        diagnostics.add_measurement(
+            NetworkDiagnosticsPlugin::ROUND_TRIP_TIME,
+            average.as_secs_f64() * 1000.0,
+        );
+    }
+
+    if let Some(log_interval) = network_diagnostics.log_interval {
+        if network_diagnostics.last_log.elapsed() >= log_interval {
+            network_diagnostics.last_log = Instant::now();
+
+            log_summary(
+                &*connection_manager,
+                &*network_diagnostics,
+                &*network_type_registry,
+            );
+        }
+    }
+}
+
+fn log_summary(
+    connection_manager: &ConnectionManager,
+    network_diagnostics: &NetworkDiagnostics,
+    network_type_registry: &NetworkTypeRegistry,
+) {
+    info!(
+        "{:>6} {:>6} {:>10} {:>10} {:>8} {:>8} {:>8} {:>7}",
+        "conn", "actor", "sent B/s", "recv B/s", "sent/s", "recv/s", "drop/s", "rtt ms"
+    );
+
+    for (connection_id, connection) in connection_manager.connections() {
+        let statistics = connection.statistics();
+        let rates = network_diagnostics
+            .rates(*connection_id)
+            .cloned()
+            .unwrap_or_default();
+        let round_trip_time = statistics
+            .round_trip_time
+            .map(|round_trip_time| format!("{:.1}", round_trip_time.as_secs_f64() * 1000.0))
+            .unwrap_or_else(|| "-".to_string());
+
+        info!(
+            "{:>6} {:>6} {:>10.0} {:>10.0} {:>8.1} {:>8.1} {:>8.1} {:>7}",
+            connection_id.0,
+            connection.actor().id().0,
+            rates.bytes_sent,
+            rates.bytes_received,
+            rates.payloads_sent,
+            rates.payloads_received,
+            rates.payloads_dropped,
+            round_trip_time
+        );
+
+        let mut payload_kinds: Vec<_> = rates
+            .payload_kinds
+            .iter()
+            .map(|(kind, kind_rates)| (kind.name(network_type_registry), kind_rates))
+            .collect();
+        payload_kinds.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (name, kind_rates) in payload_kinds {
+            info!(
+                "{:>14} {}: {:.1}/s sent ({:.0} B/s), {:.1}/s received ({:.0} B/s)",
+                "",
+                name,
+                kind_rates.sent,
+                kind_rates.data_bytes_sent,
+                kind_rates.received,
+                kind_rates.data_bytes_received
+            );
+        }
+    }
+}
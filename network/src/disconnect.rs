@@ -0,0 +1,59 @@
+use crate::*;
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// Why a connection was closed on purpose, sent to the other end in a [`Payload::Goodbye`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DisconnectReason {
+    Kicked,
+    ServerShutdown,
+    /// The client is quitting.
+    Left,
+    VersionMismatch,
+    /// Nothing was received for longer than [`NetworkSettings::timeout`].
+    Timeout,
+    Rejected(Rejection),
+    /// Moved on to the server of another zone, see [`ZonePayload::Redirect`].
+    Redirected,
+}
+
+#[derive(Debug)]
+pub enum DisconnectCause {
+    /// Reading or writing failed.
+    Error(crate::Error),
+    /// Closed by [`ConnectionManager::disconnect`] or by timing out.
+    Local(DisconnectReason),
+    /// The other end said goodbye.
+    Remote(DisconnectReason),
+}
+
+impl From<crate::Error> for DisconnectCause {
+    fn from(error: crate::Error) -> Self {
+        DisconnectCause::Error(error)
+    }
+}
+
+/// Says goodbye to every connection when the app exits.
+pub fn shutdown_system(
+    mut event_reader: Local<EventReader<AppExit>>,
+    app_exit_events: Res<Events<AppExit>>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut connection_events: ResMut<Events<ConnectionEvent>>,
+) {
+    if event_reader.iter(&app_exit_events).next().is_none() {
+        return;
+    }
+
+    let is_server = connection_manager
+        .get_local_actor()
+        .map(|actor| actor.ty().is::<Server>())
+        .unwrap_or(false);
+
+    let reason = if is_server {
+        DisconnectReason::ServerShutdown
+    } else {
+        DisconnectReason::Left
+    };
+
+    connection_events.extend(connection_manager.shutdown(reason).into_iter());
+}
@@ -1,8 +1,45 @@
+use crate::*;
+
 #[derive(Debug)]
 pub enum Error {
     Cbor(serde_cbor::Error),
+    Bincode(bincode::Error),
     Io(std::io::Error),
     DuplicateNetworkEntity,
+    FrameTooLarge(u64),
+    InvalidFrame,
+    /// Ran out of bits while reading a compact encoding.
+    UnexpectedEnd,
+    /// A frame failed authentication, it was modified, replayed or reordered on the way.
+    TamperedFrame,
+    Encryption,
+    EncryptionRequired,
+    /// The other end didn't prove it holds the pinned [`NetworkSettings::server_key`].
+    IdentityMismatch,
+    /// An [`Identity`] that isn't a valid secret key.
+    InvalidIdentity,
+    LoginRejected(LoginRejection),
+    AccountExists,
+    /// The other end refused the handshake.
+    Goodbye(DisconnectReason),
+    VersionMismatch {
+        local: u32,
+        remote: u32,
+    },
+    /// The other end runs at a different [`NetworkSettings::tick_duration`].
+    TickMismatch {
+        local: std::time::Duration,
+        remote: std::time::Duration,
+    },
+    /// A second server wants a different [`WireFormat`] than the one we already use.
+    WireFormatMismatch,
+    /// Component data that decoded to something the component can't hold.
+    InvalidComponent,
+    /// The connection went over its [`InboundBudget`].
+    BudgetExceeded,
+    /// A claimed [peer type](NetworkSettings::peer_tys) came without the right secret, or over
+    /// an unencrypted connection.
+    PeerRejected,
 }
 
 impl From<serde_cbor::Error> for Error {
@@ -11,6 +48,12 @@ impl From<serde_cbor::Error> for Error {
     }
 }
 
+impl From<bincode::Error> for Error {
+    fn from(error: bincode::Error) -> Self {
+        Error::Bincode(error)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
         Error::Io(error)
@@ -0,0 +1,173 @@
+use std::{
+    io::{self, prelude::*},
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+pub const FRAME_HEADER_LEN: usize = 8;
+pub const MAX_FRAME_LEN: usize = 1 << 24;
+
+pub fn encode_frame(bytes: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + bytes.len());
+    frame.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+    frame.extend_from_slice(bytes);
+    frame
+}
+
+/// Splits a byte stream into length prefixed frames.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new() }
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, crate::Error> {
+        if self.buffer.len() < FRAME_HEADER_LEN {
+            return Ok(None);
+        }
+
+        let mut len = [0u8; FRAME_HEADER_LEN];
+        len.copy_from_slice(&self.buffer[..FRAME_HEADER_LEN]);
+        let len = u64::from_be_bytes(len);
+
+        if len > MAX_FRAME_LEN as u64 {
+            return Err(crate::Error::FrameTooLarge(len));
+        }
+
+        let end = FRAME_HEADER_LEN + len as usize;
+
+        if self.buffer.len() < end {
+            return Ok(None);
+        }
+
+        let frame = self.buffer[FRAME_HEADER_LEN..end].to_vec();
+        self.buffer.drain(..end);
+
+        Ok(Some(frame))
+    }
+}
+
+pub struct FramedStream {
+    stream: TcpStream,
+    decoder: FrameDecoder,
+    write_buffer: Vec<u8>,
+}
+
+impl FramedStream {
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            decoder: FrameDecoder::new(),
+            write_buffer: Vec::new(),
+        }
+    }
+
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    pub fn write_frame(&mut self, bytes: &[u8]) -> Result<(), crate::Error> {
+        self.write_buffer.append(&mut encode_frame(bytes));
+        self.flush()
+    }
+
+    /// Writes as much of the buffered data as the stream accepts without blocking.
+    pub fn flush(&mut self) -> Result<(), crate::Error> {
+        while !self.write_buffer.is_empty() {
+            match self.stream.write(&self.write_buffer) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero).into()),
+                Ok(len) => {
+                    self.write_buffer.drain(..len);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads what's available on a non-blocking stream, up to about `max_read` bytes, and returns
+    /// the completed frames.
+    pub fn read_frames(&mut self, max_read: usize) -> Result<Vec<Vec<u8>>, crate::Error> {
+        let mut buf = [0u8; 4096];
+        let mut closed = false;
+        let mut read = 0;
+
+        while read < max_read {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    closed = true;
+                    break;
+                }
+                Ok(len) => {
+                    self.decoder.extend(&buf[..len]);
+                    read += len;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut frames = Vec::new();
+
+        while let Some(frame) = self.decoder.next_frame()? {
+            frames.push(frame);
+        }
+
+        // frames that arrived right before the stream closed are still delivered,
+        // the next read will report the closed stream
+        if closed && frames.is_empty() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(frames)
+    }
+
+    /// Blocks until a whole frame has been read, used while handshaking. The stream's read
+    /// timeout is for the whole frame, a peer trickling in bytes doesn't get to extend it.
+    pub fn read_frame_blocking(&mut self) -> Result<Vec<u8>, crate::Error> {
+        let timeout = self.stream.read_timeout()?;
+        let result = self.read_frame_until(timeout.map(|timeout| Instant::now() + timeout));
+
+        self.stream.set_read_timeout(timeout)?;
+        result
+    }
+
+    fn read_frame_until(&mut self, deadline: Option<Instant>) -> Result<Vec<u8>, crate::Error> {
+        let mut buf = [0u8; 4096];
+
+        loop {
+            if let Some(frame) = self.decoder.next_frame()? {
+                return Ok(frame);
+            }
+
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                if remaining == Duration::from_secs(0) {
+                    return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+                }
+
+                self.stream.set_read_timeout(Some(remaining))?;
+            }
+
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(len) => self.decoder.extend(&buf[..len]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
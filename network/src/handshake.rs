@@ -1,11 +1,67 @@
 use crate::*;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-#[derive(Serialize, Deserialize)]
-pub enum Handshake {
+/// Bumped whenever the handshake or payloads change in a way older builds can't read.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum HandshakeActorIds {
     Override {
         sender_actor_id: ActorId,
         receiver_actor_id: ActorId,
     },
     None,
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Handshake {
+    pub actor_ids: HandshakeActorIds,
+    /// Whether the sender can decompress frames.
+    pub compression: bool,
+    /// Set by [`ConnectionManager::add_connection`] if the sender wants an encrypted session.
+    pub public_key: Option<[u8; 32]>,
+    /// Whether the sender will log in once the handshake is done.
+    pub login: bool,
+    pub login_required: bool,
+    /// Whether whoever assigns actor ids issues the receiver a resume token, otherwise whether the
+    /// sender resumes with one. Tokens themselves only go out encrypted, after the login.
+    pub resume_token: bool,
+    /// Set by [`ConnectionManager::add_connection`], used by the receiver if the sender assigns
+    /// actor ids.
+    pub wire_format: WireFormat,
+    /// Sent by a listener refusing the connection, in place of handshaking.
+    #[serde(default)]
+    pub goodbye: Option<DisconnectReason>,
+    /// Missing before versions were sent, which reads as 0.
+    #[serde(default)]
+    pub version: u32,
+    /// The actor type the sender wants to be known as, see [`NetworkSettings::peer_tys`].
+    #[serde(default)]
+    pub actor_ty: Option<ActorTy>,
+    /// Missing from older builds, which aren't checked.
+    #[serde(default)]
+    pub tick_duration: Option<Duration>,
+    /// Whether the sender signs the handshakes with its [`Identity`] once encrypted.
+    #[serde(default)]
+    pub identified: bool,
+}
+
+impl Handshake {
+    pub fn new(actor_ids: HandshakeActorIds, network_settings: &NetworkSettings) -> Self {
+        Self {
+            actor_ids,
+            compression: network_settings.compression.is_some(),
+            public_key: None,
+            login: network_settings.credentials.is_some(),
+            login_required: network_settings.require_login,
+            resume_token: false,
+            wire_format: WireFormat::default(),
+            goodbye: None,
+            version: PROTOCOL_VERSION,
+            actor_ty: None,
+            tick_duration: Some(network_settings.tick_duration),
+            identified: network_settings.identity.is_some(),
+        }
+    }
+}
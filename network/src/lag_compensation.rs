@@ -0,0 +1,199 @@
+use crate::*;
+use bevy::prelude::*;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+/// Where every networked entity was over the last few ticks, to judge actions against what the
+/// sender saw when it acted. Kept on servers with [`NetworkSettings::lag_compensation`].
+pub struct PositionHistory {
+    /// In ticks.
+    length: u64,
+    tick_duration: Duration,
+    positions: HashMap<NetworkEntity, VecDeque<(NetworkTick, Vec3)>>,
+}
+
+impl PositionHistory {
+    pub fn new(length: Duration, tick_duration: Duration) -> Self {
+        Self {
+            length: (length.as_secs_f64() / tick_duration.as_secs_f64()).ceil() as u64,
+            tick_duration,
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, tick: NetworkTick, network_entity: NetworkEntity, position: Vec3) {
+        let positions = self
+            .positions
+            .entry(network_entity)
+            .or_insert(VecDeque::new());
+
+        match positions.back_mut() {
+            Some((last_tick, last_position)) if *last_tick == tick => *last_position = position,
+            _ => positions.push_back((tick, position)),
+        }
+    }
+
+    /// Forgets everything older than the history length. The latest record of an entity is kept
+    /// however old it is, it hasn't moved since.
+    pub fn prune(&mut self, now: NetworkTick) {
+        let oldest = NetworkTick(now.0.saturating_sub(self.length));
+
+        for positions in self.positions.values_mut() {
+            // the latest one before `oldest` is still where the entity was at `oldest`
+            while positions.len() > 1 && positions[1].0 <= oldest {
+                positions.pop_front();
+            }
+        }
+    }
+
+    /// Forgets a despawned entity.
+    pub fn forget(&mut self, network_entity: NetworkEntity) {
+        self.positions.remove(&network_entity);
+    }
+
+    /// Where `network_entity` was at `tick`, `None` if it didn't exist yet or the history doesn't
+    /// go back that far.
+    pub fn position_at(&self, network_entity: NetworkEntity, tick: NetworkTick) -> Option<Vec3> {
+        self.positions
+            .get(&network_entity)?
+            .iter()
+            .rev()
+            .find(|(recorded, _)| *recorded <= tick)
+            .map(|(_, position)| *position)
+    }
+
+    /// Every entity that existed at `tick` and where it was.
+    pub fn positions_at(
+        &self,
+        tick: NetworkTick,
+    ) -> impl Iterator<Item = (NetworkEntity, Vec3)> + '_ {
+        self.positions.keys().filter_map(move |network_entity| {
+            Some((*network_entity, self.position_at(*network_entity, tick)?))
+        })
+    }
+
+    /// The tick an action arriving now was taken at, judging by the sender's round trip time.
+    /// Never further back than the history goes.
+    pub fn view_tick(&self, now: NetworkTick, connection: &Connection) -> NetworkTick {
+        let round_trip_time = connection.statistics().round_trip_time.unwrap_or_default();
+
+        let ticks = (round_trip_time.as_secs_f64() / self.tick_duration.as_secs_f64()).round();
+
+        NetworkTick(now.0.saturating_sub((ticks as u64).min(self.length)))
+    }
+}
+
+/// Runs right before sending, so the recorded positions are the ones clients get this tick. Only
+/// entities that moved are recorded, the rest are still where they were last.
+pub fn position_history_system(
+    network_tick: Res<NetworkTick>,
+    mut position_history: ResMut<PositionHistory>,
+    mut recorded: Local<HashMap<Entity, NetworkEntity>>,
+    query: Query<(Entity, &NetworkEntity, &Transform), Changed<Transform>>,
+) {
+    for entity in query.removed::<NetworkEntity>() {
+        if let Some(network_entity) = recorded.remove(entity) {
+            position_history.forget(network_entity);
+        }
+    }
+
+    for (entity, network_entity, transform) in query.iter() {
+        recorded.insert(entity, *network_entity);
+        position_history.record(*network_tick, *network_entity, transform.translation);
+    }
+
+    position_history.prune(*network_tick);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(x: u64) -> Vec3 {
+        Vec3::new(x as f32, 0.0, 0.0)
+    }
+
+    /// Five ticks of history, with an entity recorded on ticks 1 to 10.
+    fn history() -> PositionHistory {
+        let mut history =
+            PositionHistory::new(Duration::from_millis(500), Duration::from_millis(100));
+
+        for tick in 1..=10 {
+            history.record(NetworkTick(tick), NetworkEntity(1), position(tick));
+        }
+
+        history.prune(NetworkTick(10));
+        history
+    }
+
+    #[test]
+    fn positions_hold_between_records() {
+        let mut history = PositionHistory::new(Duration::from_secs(1), Duration::from_millis(100));
+        history.record(NetworkTick(2), NetworkEntity(1), position(2));
+        history.record(NetworkTick(4), NetworkEntity(1), position(4));
+
+        assert_eq!(history.position_at(NetworkEntity(1), NetworkTick(1)), None);
+        assert_eq!(
+            history.position_at(NetworkEntity(1), NetworkTick(2)),
+            Some(position(2))
+        );
+        assert_eq!(
+            history.position_at(NetworkEntity(1), NetworkTick(3)),
+            Some(position(2))
+        );
+        assert_eq!(
+            history.position_at(NetworkEntity(1), NetworkTick(9)),
+            Some(position(4))
+        );
+    }
+
+    #[test]
+    fn history_reaches_back_to_the_window() {
+        let history = history();
+
+        assert_eq!(
+            history.position_at(NetworkEntity(1), NetworkTick(5)),
+            Some(position(5))
+        );
+        assert_eq!(
+            history.position_at(NetworkEntity(1), NetworkTick(7)),
+            Some(position(7))
+        );
+        assert_eq!(
+            history.position_at(NetworkEntity(1), NetworkTick(10)),
+            Some(position(10))
+        );
+    }
+
+    #[test]
+    fn nothing_beyond_the_window_is_kept() {
+        let history = history();
+
+        assert_eq!(history.position_at(NetworkEntity(1), NetworkTick(4)), None);
+        assert_eq!(history.positions_at(NetworkTick(4)).count(), 0);
+    }
+
+    #[test]
+    fn entities_that_stop_moving_are_kept() {
+        let mut history = history();
+
+        history.prune(NetworkTick(16));
+
+        assert_eq!(
+            history.position_at(NetworkEntity(1), NetworkTick(16)),
+            Some(position(10))
+        );
+        assert_eq!(history.position_at(NetworkEntity(1), NetworkTick(9)), None);
+    }
+
+    #[test]
+    fn forgotten_entities_are_gone() {
+        let mut history = history();
+
+        history.forget(NetworkEntity(1));
+
+        assert_eq!(history.position_at(NetworkEntity(1), NetworkTick(10)), None);
+    }
+}
@@ -1,31 +1,92 @@
+extern crate self as network;
+
+mod access_control;
+mod account;
+mod admin;
+mod bits;
+mod budget;
+mod client_connection;
 mod connection_manager;
 mod message;
 #[macro_use]
 mod network_type_uuid;
 mod communication;
+mod compact_transform;
 mod component_sync;
+mod compression;
+mod conditioner;
+mod crypto;
+mod diagnostics;
+mod disconnect;
 mod error;
+mod frame;
 mod handshake;
+mod lag_compensation;
 mod listener;
 mod network_entity;
+mod network_log;
 mod plugin;
+mod replay;
+mod room;
+mod session;
 mod settings;
+mod simulation;
 mod spawnable;
+mod statistics;
 mod syncable_component;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+mod wire_format;
+mod zone;
+pub use access_control::*;
+pub use account::*;
+pub use admin::*;
+pub use bits::*;
+pub use budget::*;
+pub use client_connection::*;
 pub use communication::*;
+pub use compact_transform::*;
 pub use component_sync::*;
+pub use compression::*;
+pub use conditioner::*;
 pub use connection_manager::*;
+pub use crypto::*;
+pub use diagnostics::*;
+pub use disconnect::*;
 pub use error::*;
+pub use frame::*;
 pub use handshake::*;
+pub use lag_compensation::*;
 pub use listener::*;
 pub use message::*;
+pub use network_derive::{NetworkTypeUuid, SyncableComponent};
 pub use network_entity::*;
+pub use network_log::*;
 pub use network_type_uuid::*;
 pub use plugin::*;
+pub use replay::*;
+pub use room::*;
 pub use serde::{Deserialize, Serialize};
+pub use session::*;
 pub use settings::*;
+pub use simulation::*;
 pub use spawnable::*;
+pub use statistics::*;
 pub use syncable_component::*;
+#[cfg(any(test, feature = "testing"))]
+pub use testing::*;
+pub use wire_format::*;
+pub use zone::*;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use bevy::reflect::{
+        serde::{ReflectDeserializer, ReflectSerializer},
+        Reflect, TypeRegistry, Uuid,
+    };
+    pub use serde;
+    pub use serde_cbor;
+}
 
 pub struct Server;
 pub struct Client;
@@ -1,43 +1,202 @@
 use crate::*;
 use bevy::prelude::*;
-use std::net::TcpListener;
+use std::{
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long an incoming connection gets for its whole handshake.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Incoming connections handshaking at once, any more are turned away as if the server was full.
+pub const MAX_PENDING_HANDSHAKES: usize = 64;
+
+type HandshakeResult = Result<(ConnectionManager, ConnectionEvent), crate::Error>;
+
+struct PendingHandshake {
+    addr: SocketAddr,
+    /// Shut down once the deadline passes, which fails whatever the handshake is blocked on.
+    stream: TcpStream,
+    deadline: Instant,
+    receiver: Mutex<mpsc::Receiver<HandshakeResult>>,
+}
 
 pub struct Listener {
     inner: TcpListener,
+    pending: Vec<PendingHandshake>,
 }
 
 impl Listener {
     pub fn new(listener: TcpListener) -> Self {
         listener.set_nonblocking(true).unwrap();
 
-        Self { inner: listener }
+        Self {
+            inner: listener,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.inner.local_addr()
     }
 }
 
+/// Handshakes with `stream` on another thread, key exchange and logins included, so a slow or
+/// stalling peer never holds up the app.
+fn start_handshake(
+    stream: TcpStream,
+    addr: SocketAddr,
+    connection_manager: &mut ConnectionManager,
+    network_settings: &NetworkSettings,
+    account_store: &AccountStore,
+) -> Result<PendingHandshake, crate::Error> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: connection_manager.generate_actor_id(),
+        sender_actor_id: connection_manager.get_local_actor().unwrap().id(),
+    };
+
+    let mut detached = connection_manager.detached();
+    detached.set_keep_local_actor_id(true);
+
+    let handshake = Handshake::new(actor_ids, network_settings);
+    let network_settings = network_settings.clone();
+    let mut account_store = account_store.clone();
+    let watched = stream.try_clone()?;
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let result = detached
+            .add_connection(
+                stream,
+                network_settings.connection_ty,
+                handshake,
+                &network_settings,
+                Some(&mut account_store),
+            )
+            .map(|event| (detached, event));
+
+        let _ = sender.send(result);
+    });
+
+    Ok(PendingHandshake {
+        addr,
+        stream: watched,
+        deadline: Instant::now() + HANDSHAKE_TIMEOUT,
+        receiver: Mutex::new(receiver),
+    })
+}
+
 pub fn listening_system(
-    listener: Res<Listener>,
+    mut listener: ResMut<Listener>,
     network_settings: Res<NetworkSettings>,
     mut connection_manager: ResMut<ConnectionManager>,
+    account_store: Res<AccountStore>,
+    mut access_control: ResMut<AccessControl>,
     mut connection_events: ResMut<Events<ConnectionEvent>>,
 ) {
-    for stream in listener.inner.incoming() {
-        match stream {
-            Ok(stream) => {
-                let handshake = Handshake::Override {
-                    receiver_actor_id: connection_manager.generate_actor_id(),
-                    sender_actor_id: connection_manager.get_local_actor().unwrap().id(),
-                };
-
-                let event = connection_manager.add_connection(
-                    stream,
-                    network_settings.connection_ty,
-                    handshake,
-                );
-
-                connection_events.send(event);
+    access_control.prune();
+
+    let listener = &mut *listener;
+
+    loop {
+        let stream = match listener.inner.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            Err(e) => {
+                log::warn!("{:?}", e);
+                break;
+            }
+        };
+
+        let addr = match stream.peer_addr() {
+            Ok(addr) => addr,
+            Err(e) => {
+                log::warn!("{:?}", e);
+                continue;
+            }
+        };
+
+        // links to other servers claim a peer type and aren't players, handshakes still going
+        // might be
+        let players = connection_manager
+            .connections()
+            .filter(|(_, connection)| connection.actor().ty() == network_settings.connection_ty)
+            .count()
+            + listener.pending.len();
+
+        let checked = if listener.pending.len() >= MAX_PENDING_HANDSHAKES {
+            Err(Rejection::ServerFull)
+        } else {
+            access_control.check_addr(addr.ip(), players)
+        };
+
+        if let Err(rejection) = checked {
+            log::info!("Rejected {}: {:?}", addr, rejection);
+
+            let reason = DisconnectReason::Rejected(rejection);
+
+            if let Err(e) = reject_stream(stream, reason, &*network_settings) {
+                log::warn!("Failed to send rejection: {:?}", e);
             }
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return,
-            Err(e) => log::warn!("{:?}", e),
+
+            continue;
         }
+
+        match start_handshake(
+            stream,
+            addr,
+            &mut *connection_manager,
+            &*network_settings,
+            &*account_store,
+        ) {
+            Ok(pending) => listener.pending.push(pending),
+            Err(e) => log::warn!("Failed to start a handshake with {}: {:?}", addr, e),
+        }
+    }
+
+    let now = Instant::now();
+
+    for pending in std::mem::replace(&mut listener.pending, Vec::new()) {
+        let received = pending.receiver.lock().unwrap().try_recv();
+
+        let result = match received {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) if now < pending.deadline => {
+                listener.pending.push(pending);
+                continue;
+            }
+            Err(_) => {
+                log::warn!("Handshake with {} timed out", pending.addr);
+                let _ = pending.stream.shutdown(Shutdown::Both);
+                continue;
+            }
+        };
+
+        let event = match result {
+            Ok((connected, event)) => connection_manager.attach(connected, event),
+            Err(e) => {
+                log::warn!("Handshake with {} failed: {:?}", pending.addr, e);
+                continue;
+            }
+        };
+
+        if let ConnectionEvent::Connected {
+            actor, player_id, ..
+        } = &event
+        {
+            if let Err(rejection) = access_control.check_player(pending.addr.ip(), *player_id) {
+                log::info!("Rejected {} {:?}: {:?}", pending.addr, player_id, rejection);
+
+                // it never connected as far as the rest of the app knows
+                connection_manager.disconnect(actor.id(), DisconnectReason::Rejected(rejection));
+                continue;
+            }
+        }
+
+        connection_events.send(event);
     }
 }
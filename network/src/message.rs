@@ -1,8 +1,8 @@
 use crate::*;
 use bevy::reflect::Uuid;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub enum Payload {
     ComponentUpdate {
         target_entity: NetworkEntity,
@@ -13,6 +13,17 @@ pub enum Payload {
         network_entity: NetworkEntity,
         data: Vec<u8>,
     },
+    /// Answered with a [`Payload::Pong`] by the connection itself, never shows up as a message.
+    Ping {
+        id: u64,
+    },
+    Pong {
+        id: u64,
+    },
+    Admin(AdminPayload),
+    /// Sent right before closing a connection on purpose.
+    Goodbye(DisconnectReason),
+    Zone(ZonePayload),
 }
 
 #[derive(Clone, Debug)]
@@ -22,17 +33,18 @@ pub struct Message {
     pub receiver: Actor,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum NetworkTarget {
     ActorId(ActorId),
     ActorTy(ActorTy),
+    Room(String),
     All,
 }
 
 #[derive(Default)]
 pub struct NetworkHandle {
     payloads: Vec<(NetworkTarget, Payload)>,
-    spawn_messages: Vec<(NetworkTarget, Vec<u8>)>,
+    spawn_messages: Vec<(Option<NetworkEntity>, NetworkTarget, Box<dyn Spawnable>)>,
 }
 
 impl NetworkHandle {
@@ -43,10 +55,20 @@ impl NetworkHandle {
         }
     }
 
-    pub fn spawn<T: Spawnable>(&mut self, target: NetworkTarget, spawnable: T) {
-        let spawnable: Box<dyn Spawnable> = Box::new(spawnable);
-        let data = serde_cbor::to_vec(&spawnable).unwrap();
-        self.spawn_messages.push((target, data));
+    pub fn spawn<T: Spawnable + 'static>(&mut self, target: NetworkTarget, spawnable: T) {
+        self.spawn_messages
+            .push((None, target, Box::new(spawnable)));
+    }
+
+    /// Spawns with a network entity from [`NetworkEntityRegistry::generate_network_entity`].
+    pub fn spawn_as(
+        &mut self,
+        network_entity: NetworkEntity,
+        target: NetworkTarget,
+        spawnable: Box<dyn Spawnable>,
+    ) {
+        self.spawn_messages
+            .push((Some(network_entity), target, spawnable));
     }
 
     pub fn sync_component(
@@ -71,9 +93,14 @@ impl NetworkHandle {
         network_entity_registry: &mut NetworkEntityRegistry,
         spawn_manager: &mut SpawnManager,
         connection_manager: &ConnectionManager,
+        rooms: &Rooms,
     ) {
-        for (target, data) in std::mem::replace(&mut self.spawn_messages, Vec::new()) {
-            let network_entity = network_entity_registry.generate_network_entity();
+        for (network_entity, target, spawnable) in
+            std::mem::replace(&mut self.spawn_messages, Vec::new())
+        {
+            let network_entity =
+                network_entity.unwrap_or_else(|| network_entity_registry.generate_network_entity());
+            let data = connection_manager.wire_format().encode(&spawnable).unwrap();
 
             let payload = Payload::Spawn {
                 network_entity,
@@ -82,8 +109,8 @@ impl NetworkHandle {
 
             spawn_manager.register_spawn(network_entity, target.clone(), payload.clone());
 
-            for connection_id in connection_manager.get_targeted_connection_ids(&target) {
-                spawn_manager.confirm_spawn(connection_id, network_entity);
+            for actor_id in connection_manager.get_targeted_actor_ids(&target, rooms) {
+                spawn_manager.confirm_spawn(actor_id, network_entity);
             }
 
             self.add_payload(target, payload);
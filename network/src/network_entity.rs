@@ -1,4 +1,3 @@
-use crate::*;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -35,6 +34,20 @@ impl NetworkEntityRegistry {
         entity
     }
 
+    pub fn remove(&mut self, network_entity: &NetworkEntity) -> Option<Entity> {
+        self.network_entities.remove(network_entity)
+    }
+
+    /// Forgets every network entity, returning their entities.
+    pub fn clear(&mut self) -> Vec<Entity> {
+        self.next_network_entity = NetworkEntity(0);
+
+        self.network_entities
+            .drain()
+            .map(|(_, entity)| entity)
+            .collect()
+    }
+
     pub fn insert(
         &mut self,
         network_entity: NetworkEntity,
@@ -44,7 +57,7 @@ impl NetworkEntityRegistry {
             self.network_entities.insert(network_entity, entity);
 
             if network_entity.0 >= self.next_network_entity.0 {
-                self.next_network_entity.0 = network_entity.0 + 1;
+                self.next_network_entity.0 = network_entity.0.saturating_add(1);
             }
 
             Ok(())
@@ -0,0 +1,111 @@
+use crate::*;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogDirection {
+    Incoming,
+    Outgoing,
+}
+
+#[derive(Clone, Debug)]
+pub struct NetworkLogEntry {
+    pub tick: NetworkTick,
+    pub direction: LogDirection,
+    pub kind: PayloadKind,
+    pub network_entity: Option<NetworkEntity>,
+    /// Bytes of payload data, see [`PayloadCounts`].
+    pub size: usize,
+    /// Who sent an incoming payload.
+    pub sender: Option<Actor>,
+    /// Where an outgoing payload went.
+    pub target: Option<NetworkTarget>,
+}
+
+impl NetworkLogEntry {
+    fn new(tick: NetworkTick, direction: LogDirection, payload: &Payload) -> Self {
+        let network_entity = match payload {
+            Payload::ComponentUpdate { target_entity, .. } => Some(*target_entity),
+            Payload::Spawn { network_entity, .. } => Some(*network_entity),
+            Payload::Zone(ZonePayload::Left(network_entity)) => Some(*network_entity),
+            _ => None,
+        };
+
+        Self {
+            tick,
+            direction,
+            kind: PayloadKind::of(payload),
+            network_entity,
+            size: data_len(payload),
+            sender: None,
+            target: None,
+        }
+    }
+
+    /// The synced component's type name, otherwise the kind of payload.
+    pub fn type_name(&self, network_type_registry: &NetworkTypeRegistry) -> String {
+        self.kind.name(network_type_registry)
+    }
+
+    /// Whether the type name contains `filter`, ignoring case.
+    pub fn matches(&self, filter: &str, network_type_registry: &NetworkTypeRegistry) -> bool {
+        self.type_name(network_type_registry)
+            .to_lowercase()
+            .contains(&filter.to_lowercase())
+    }
+}
+
+/// The last few payloads sent and received, kept with [`NetworkSettings::log`]. Does nothing
+/// when created with `default`.
+#[derive(Default)]
+pub struct NetworkLog {
+    capacity: usize,
+    entries: VecDeque<NetworkLogEntry>,
+}
+
+impl NetworkLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record_incoming(&mut self, tick: NetworkTick, messages: &[Message]) {
+        for message in messages {
+            let mut entry = NetworkLogEntry::new(tick, LogDirection::Incoming, &message.payload);
+            entry.sender = Some(message.sender.clone());
+
+            self.push(entry);
+        }
+    }
+
+    pub fn record_outgoing(&mut self, tick: NetworkTick, payloads: &[(NetworkTarget, Payload)]) {
+        for (target, payload) in payloads {
+            let mut entry = NetworkLogEntry::new(tick, LogDirection::Outgoing, payload);
+            entry.target = Some(target.clone());
+
+            self.push(entry);
+        }
+    }
+
+    fn push(&mut self, entry: NetworkLogEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(entry);
+    }
+
+    /// Oldest first.
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &NetworkLogEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
@@ -1,4 +1,6 @@
-use bevy::reflect::Uuid;
+use crate::*;
+use bevy::{prelude::*, reflect::Uuid};
+use std::collections::HashMap;
 
 pub trait NetworkTypeUuid {
     const UUID: Uuid;
@@ -12,3 +14,163 @@ macro_rules! network_uuid {
         }
     };
 }
+
+const FNV_OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// 128 bit FNV-1a, used by `#[derive(NetworkTypeUuid)]` to turn a type path into a uuid that
+/// stays the same between builds.
+pub const fn type_path_hash(path: &str) -> u128 {
+    fnv(FNV_OFFSET_BASIS, path.as_bytes())
+}
+
+/// Mixes the uuid of a type parameter into `hash`, used by `#[derive(NetworkTypeUuid)]` so every
+/// instance of a generic type gets a uuid of its own.
+pub const fn type_param_hash(hash: u128, param: &Uuid) -> u128 {
+    fnv(hash, param.as_bytes())
+}
+
+const fn fnv(mut hash: u128, bytes: &[u8]) -> u128 {
+    let mut i = 0;
+
+    while i < bytes.len() {
+        hash ^= bytes[i] as u128;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+
+    hash
+}
+
+/// Applies a `ComponentUpdate` from `sender` to `entity`.
+pub type ComponentUpdateHandler = fn(&mut World, &Resources, Entity, &Actor, &[u8]);
+
+/// Encodes the component of `entity`, if it has one.
+pub type ComponentSnapshotHandler = fn(&World, &Resources, Entity) -> Option<Vec<u8>>;
+
+/// Overwrites the component of `entity` no matter who owns it.
+pub type ComponentRestoreHandler = fn(&mut World, &Resources, Entity, &[u8]);
+
+pub struct NetworkType {
+    pub name: &'static str,
+    pub component_update_handler: Option<ComponentUpdateHandler>,
+    pub component_snapshot_handler: Option<ComponentSnapshotHandler>,
+    pub component_restore_handler: Option<ComponentRestoreHandler>,
+}
+
+/// Every [`NetworkTypeUuid`] the app uses, so two types can't end up sharing a uuid.
+#[derive(Default)]
+pub struct NetworkTypeRegistry {
+    types: HashMap<Uuid, NetworkType>,
+}
+
+impl NetworkTypeRegistry {
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::new(),
+        }
+    }
+
+    /// Panics if another type already registered the same uuid.
+    pub fn register<T: NetworkTypeUuid + 'static>(&mut self) {
+        let name = std::any::type_name::<T>();
+
+        match self.types.get(&T::UUID) {
+            Some(existing) if existing.name != name => panic!(
+                "{} and {} have the same network type uuid {}",
+                existing.name,
+                name,
+                T::UUID
+            ),
+            Some(_) => (),
+            None => {
+                self.types.insert(
+                    T::UUID,
+                    NetworkType {
+                        name,
+                        component_update_handler: None,
+                        component_snapshot_handler: None,
+                        component_restore_handler: None,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn register_component<T: SyncableComponent + Send + Sync + 'static>(&mut self) {
+        self.register_component_with::<T>(
+            apply_component_update::<T>,
+            snapshot_component::<T>,
+            restore_component::<T>,
+        );
+    }
+
+    /// Registers a component that isn't synced through [`SyncableComponent`].
+    pub fn register_component_with<T: NetworkTypeUuid + 'static>(
+        &mut self,
+        update_handler: ComponentUpdateHandler,
+        snapshot_handler: ComponentSnapshotHandler,
+        restore_handler: ComponentRestoreHandler,
+    ) {
+        self.register::<T>();
+
+        let network_type = self.types.get_mut(&T::UUID).unwrap();
+        network_type.component_update_handler = Some(update_handler);
+        network_type.component_snapshot_handler = Some(snapshot_handler);
+        network_type.component_restore_handler = Some(restore_handler);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Uuid, &NetworkType)> {
+        self.types.iter()
+    }
+
+    pub fn get(&self, uuid: &Uuid) -> Option<&NetworkType> {
+        self.types.get(uuid)
+    }
+
+    pub fn get_name(&self, uuid: &Uuid) -> Option<&'static str> {
+        self.types.get(uuid).map(|network_type| network_type.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sword;
+    network_uuid!(Sword = 1);
+
+    struct Shield;
+    network_uuid!(Shield = 1);
+
+    #[test]
+    fn types_are_named_by_uuid() {
+        let mut network_type_registry = NetworkTypeRegistry::new();
+        network_type_registry.register::<Sword>();
+        network_type_registry.register::<Sword>();
+
+        assert_eq!(network_type_registry.iter().count(), 1);
+        assert_eq!(
+            network_type_registry.get_name(&Sword::UUID),
+            Some(std::any::type_name::<Sword>())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "have the same network type uuid")]
+    fn shared_uuids_panic() {
+        let mut network_type_registry = NetworkTypeRegistry::new();
+        network_type_registry.register::<Sword>();
+        network_type_registry.register::<Shield>();
+    }
+
+    #[test]
+    fn type_path_hashes_are_stable_and_distinct() {
+        assert_eq!(type_path_hash("game::Sword"), type_path_hash("game::Sword"));
+        assert_ne!(
+            type_path_hash("game::Sword"),
+            type_path_hash("game::Shield")
+        );
+        assert_eq!(type_path_hash(""), 0x6c62272e07bb014262b821756295c58d);
+    }
+}
@@ -1,6 +1,10 @@
 use crate::*;
 use bevy::prelude::*;
-use std::net::{TcpListener, TcpStream};
+use std::{
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::Mutex,
+};
 
 pub mod stage {
     pub const NETWORK_SEND: &'static str = "network_send";
@@ -8,11 +12,21 @@ pub mod stage {
     pub const NETWORK_SYNC_MARK: &'static str = "network_sync_mark";
     pub const NETWORK_RECEIVE: &'static str = "network_receive";
     pub const NETWORK_POST_RECEIVE: &'static str = "network_post_receive";
+    /// Gameplay, runs once per tick on every end, see [`SimTime`](crate::SimTime).
+    pub const SIMULATION: &'static str = "simulation";
 }
 
 pub enum ConnectionMethod {
     Stream(TcpStream),
+    /// Connects in the background once the app runs, see [`ClientConnection`].
+    Connect(String),
+    /// Waits for [`ClientConnection::connect`].
+    Deferred,
     Listener(TcpListener),
+    /// Plays back a recording instead of connecting, see [`Replay`].
+    Replay(PathBuf),
+    /// A connection manager that has already finished its handshakes, taken on build.
+    Connected(Mutex<Option<ConnectionManager>>),
 }
 
 pub trait AppBuilderExt {
@@ -23,10 +37,16 @@ pub trait AppBuilderExt {
     ) -> &mut AppBuilder {
         let app_builder = self.app_builder();
 
-        app_builder.add_system_to_stage(
-            stage::NETWORK_POST_RECEIVE,
-            component_sync_receiving_system::<T>,
-        );
+        if !app_builder.resources().contains::<NetworkTypeRegistry>() {
+            app_builder.init_resource::<NetworkTypeRegistry>();
+        }
+
+        app_builder
+            .resources()
+            .get_mut::<NetworkTypeRegistry>()
+            .unwrap()
+            .register_component::<T>();
+
         app_builder
             .add_system_to_stage(stage::NETWORK_PRE_SEND, component_sync_sending_system::<T>);
 
@@ -37,6 +57,45 @@ pub trait AppBuilderExt {
 
         app_builder
     }
+
+    /// Syncs `Transform` quantized with `settings` instead of with reflection, use it instead of
+    /// `add_component_sync::<Transform>()` on every end. Entities still need a
+    /// `ComponentSync<Transform>`.
+    fn add_compact_transform_sync(
+        &mut self,
+        settings: CompactTransformSettings,
+    ) -> &mut AppBuilder {
+        let app_builder = self.app_builder();
+
+        if !app_builder.resources().contains::<NetworkTypeRegistry>() {
+            app_builder.init_resource::<NetworkTypeRegistry>();
+        }
+
+        app_builder
+            .resources()
+            .get_mut::<NetworkTypeRegistry>()
+            .unwrap()
+            .register_component_with::<CompactTransform>(
+                apply_compact_transform_update,
+                snapshot_compact_transform,
+                restore_compact_transform,
+            );
+
+        app_builder.add_resource(settings);
+
+        app_builder.add_system_to_stage(stage::NETWORK_PRE_SEND, compact_transform_sending_system);
+
+        app_builder.add_system_to_stage(
+            stage::NETWORK_SYNC_MARK,
+            component_sync_marking_system::<Transform>,
+        );
+        app_builder.add_system_to_stage(
+            stage::NETWORK_SYNC_MARK,
+            component_sync_connect_system::<Transform>,
+        );
+
+        app_builder
+    }
 }
 
 impl AppBuilderExt for AppBuilder {
@@ -48,6 +107,8 @@ impl AppBuilderExt for AppBuilder {
 pub struct NetworkPlugin {
     settings: NetworkSettings,
     connection_method: ConnectionMethod,
+    /// Extra connections made on build, each with its own actor type.
+    outbound: Vec<(TcpStream, ActorTy)>,
 }
 
 impl NetworkPlugin {
@@ -55,6 +116,7 @@ impl NetworkPlugin {
         Self {
             settings: NetworkSettings::server(),
             connection_method: ConnectionMethod::Listener(listener),
+            outbound: Vec::new(),
         }
     }
 
@@ -62,73 +124,246 @@ impl NetworkPlugin {
         Self {
             settings: NetworkSettings::client(),
             connection_method: ConnectionMethod::Stream(stream),
+            outbound: Vec::new(),
+        }
+    }
+
+    pub fn connect(addr: impl Into<String>) -> Self {
+        Self {
+            settings: NetworkSettings::client(),
+            connection_method: ConnectionMethod::Connect(addr.into()),
+            outbound: Vec::new(),
+        }
+    }
+
+    pub fn deferred() -> Self {
+        Self {
+            settings: NetworkSettings::client(),
+            connection_method: ConnectionMethod::Deferred,
+            outbound: Vec::new(),
         }
     }
+
+    pub fn replay(path: impl Into<PathBuf>) -> Self {
+        Self {
+            settings: NetworkSettings::client(),
+            connection_method: ConnectionMethod::Replay(path.into()),
+            outbound: Vec::new(),
+        }
+    }
+
+    pub fn connected(connection_manager: ConnectionManager) -> Self {
+        Self {
+            settings: NetworkSettings::client(),
+            connection_method: ConnectionMethod::Connected(Mutex::new(Some(connection_manager))),
+            outbound: Vec::new(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: NetworkSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Also connects over `stream`, like to a chat server or another game server. Its actor is
+    /// `actor_ty` and gets an id from us, only the main connection assigns our own id.
+    pub fn with_outbound(mut self, stream: TcpStream, actor_ty: ActorTy) -> Self {
+        self.outbound.push((stream, actor_ty));
+        self
+    }
 }
 
 impl Plugin for NetworkPlugin {
     fn build(&self, app_builder: &mut AppBuilder) {
+        // every stage of a tick runs once per SimTime step, so outside of replays NetworkTick
+        // equals SimTime::steps
         app_builder.add_stage_after(
             bevy::app::stage::PRE_UPDATE,
             stage::NETWORK_POST_RECEIVE,
-            SystemStage::parallel().with_run_criteria(bevy::core::FixedTimestep::step(1.0 / 20.0)),
+            SystemStage::parallel().with_run_criteria(sim_step_run_criteria),
         );
         app_builder.add_stage_before(
             stage::NETWORK_POST_RECEIVE,
             stage::NETWORK_RECEIVE,
-            SystemStage::parallel().with_run_criteria(bevy::core::FixedTimestep::step(1.0 / 20.0)),
+            SystemStage::parallel().with_run_criteria(sim_time_run_criteria),
         );
 
         app_builder.add_stage_before(
             bevy::app::stage::POST_UPDATE,
             stage::NETWORK_SEND,
-            SystemStage::parallel().with_run_criteria(bevy::core::FixedTimestep::step(1.0 / 20.0)),
+            SystemStage::parallel().with_run_criteria(sim_step_run_criteria),
         );
         app_builder.add_stage_before(
             stage::NETWORK_SEND,
             stage::NETWORK_PRE_SEND,
-            SystemStage::parallel().with_run_criteria(bevy::core::FixedTimestep::step(1.0 / 20.0)),
+            SystemStage::parallel().with_run_criteria(sim_step_run_criteria),
         );
         app_builder.add_stage_before(
             stage::NETWORK_PRE_SEND,
             stage::NETWORK_SYNC_MARK,
             SystemStage::parallel(),
         );
+        app_builder.add_stage_after(
+            stage::NETWORK_POST_RECEIVE,
+            stage::SIMULATION,
+            SystemStage::parallel().with_run_criteria(sim_step_run_criteria),
+        );
 
         let mut connection_manager = ConnectionManager::new(self.settings.actor_ty);
+        connection_manager.set_wire_format(self.settings.wire_format);
 
         match &self.connection_method {
             ConnectionMethod::Stream(stream) => {
-                let handshake = Handshake::None;
+                let handshake = Handshake::new(HandshakeActorIds::None, &self.settings);
 
-                connection_manager.add_connection(
-                    stream.try_clone().unwrap(),
-                    self.settings.connection_ty,
-                    handshake,
-                );
+                connection_manager
+                    .add_connection(
+                        stream.try_clone().unwrap(),
+                        self.settings.connection_ty,
+                        handshake,
+                        &self.settings,
+                        None,
+                    )
+                    .expect("Failed to connect");
             }
+            ConnectionMethod::Connect(_) | ConnectionMethod::Deferred => (),
             ConnectionMethod::Listener(listener) => {
+                connection_manager.set_keep_local_actor_id(true);
+
                 app_builder.add_resource(Listener::new(listener.try_clone().unwrap()));
 
+                if !app_builder.resources().contains::<AccountStore>() {
+                    app_builder.init_resource::<AccountStore>();
+                }
+
+                if !app_builder.resources().contains::<AccessControl>() {
+                    app_builder.init_resource::<AccessControl>();
+                }
+
                 app_builder.add_system(listening_system);
             }
+            ConnectionMethod::Replay(path) => {
+                let replay = Replay::open(path).expect("Failed to open replay");
+
+                connection_manager.set_wire_format(replay.wire_format());
+                app_builder.add_resource(replay);
+            }
+            ConnectionMethod::Connected(connected) => {
+                connection_manager = connected
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("NetworkPlugin::connected can only be built once");
+            }
+        }
+
+        connection_manager.set_peer_tys(self.settings.peer_tys.clone());
+
+        let client_connection = match &self.connection_method {
+            ConnectionMethod::Listener(_) | ConnectionMethod::Replay(_) => None,
+            ConnectionMethod::Connect(addr) => {
+                let mut client_connection = ClientConnection::default();
+                client_connection.connect(addr.clone());
+
+                Some(client_connection)
+            }
+            ConnectionMethod::Deferred => Some(ClientConnection::default()),
+            ConnectionMethod::Stream(_) | ConnectionMethod::Connected(_) => connection_manager
+                .connections()
+                .filter_map(|(_, connection)| Some((connection.addr()?, connection.actor().id())))
+                .next()
+                .map(|(addr, server)| ClientConnection::connected(addr.to_string(), server)),
+        };
+
+        // only the main connection decides who we are
+        let keep_local_actor_id = connection_manager.keep_local_actor_id();
+        connection_manager.set_keep_local_actor_id(true);
+
+        for (stream, actor_ty) in &self.outbound {
+            let handshake = Handshake::new(HandshakeActorIds::None, &self.settings);
+
+            connection_manager
+                .add_connection(
+                    stream.try_clone().unwrap(),
+                    *actor_ty,
+                    handshake,
+                    &self.settings,
+                    None,
+                )
+                .expect("Failed to connect");
+        }
+
+        connection_manager.set_keep_local_actor_id(keep_local_actor_id);
+
+        if let Some(client_connection) = client_connection {
+            app_builder.add_resource(client_connection);
+            // after receiving_system, so disconnects are seen the tick they happen
+            app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, client_connection_system);
+            app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, zone_client_system);
+        }
+
+        if let ConnectionMethod::Replay(_) = &self.connection_method {
+            app_builder.add_system_to_stage(stage::NETWORK_RECEIVE, replay_system);
+        } else {
+            connection_manager.set_timeout(self.settings.timeout);
+
+            app_builder.add_system_to_stage(stage::NETWORK_RECEIVE, receiving_system);
+            app_builder.add_system_to_stage(bevy::app::stage::LAST, shutdown_system);
+        }
+
+        if let Some(length) = self.settings.lag_compensation {
+            app_builder.add_resource(PositionHistory::new(length, self.settings.tick_duration));
+            app_builder.add_system_to_stage(stage::NETWORK_PRE_SEND, position_history_system);
         }
 
+        let recorder = match &self.settings.record {
+            Some(path) => Recorder::create(path, connection_manager.wire_format())
+                .expect("Failed to create recording"),
+            None => Recorder::default(),
+        };
+
+        app_builder.add_resource(recorder);
+        app_builder.add_resource(match self.settings.log {
+            Some(capacity) => NetworkLog::new(capacity),
+            None => NetworkLog::default(),
+        });
+
         app_builder.add_resource(connection_manager);
         app_builder.add_resource(self.settings.clone());
 
         app_builder.init_resource::<NetworkHandle>();
         app_builder.init_resource::<NetworkEntityRegistry>();
         app_builder.init_resource::<SpawnSystemEventReader>();
+        app_builder.init_resource::<ComponentUpdateEventReader>();
         app_builder.init_resource::<SpawnManager>();
+        app_builder.init_resource::<Rooms>();
+
+        if !app_builder.resources().contains::<NetworkTypeRegistry>() {
+            app_builder.init_resource::<NetworkTypeRegistry>();
+        }
+
+        {
+            let mut network_type_registry = app_builder
+                .resources()
+                .get_mut::<NetworkTypeRegistry>()
+                .unwrap();
+
+            network_type_registry.register::<Server>();
+            network_type_registry.register::<Client>();
+        }
+        app_builder.init_resource::<NetworkTick>();
+        app_builder.add_resource(SimTime::new(self.settings.tick_duration));
 
         app_builder.add_event::<ConnectionEvent>();
         app_builder.add_event::<Message>();
+        app_builder.add_event::<RoomEvent>();
 
         app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, spawn_system);
-        app_builder.add_system_to_stage(stage::NETWORK_RECEIVE, receiving_system);
+        app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, component_update_system);
         app_builder.add_system_to_stage(stage::NETWORK_SEND, sending_system);
         app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, disconnect_handler_system);
         app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, spawn_detection_system);
+        app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, admin_message_system);
+        app_builder.add_system_to_stage(bevy::app::stage::POST_UPDATE, room_event_system);
     }
 }
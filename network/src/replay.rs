@@ -0,0 +1,341 @@
+use crate::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{prelude::*, BufWriter},
+    path::Path,
+    time::Instant,
+};
+
+/// Counts network ticks, advanced once per receive.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct NetworkTick(pub u64);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Record {
+    /// Written first and again whenever it changes, later payload data is encoded with this.
+    Format(WireFormat),
+    Incoming {
+        tick: u64,
+        /// Milliseconds since the recording started.
+        time: u64,
+        sender: Actor,
+        receiver: Actor,
+        payload: Payload,
+    },
+    Outgoing {
+        tick: u64,
+        time: u64,
+        target: NetworkTarget,
+        payload: Payload,
+    },
+}
+
+/// Writes every message to a file of length prefixed CBOR [`Record`]s, does nothing unless
+/// created with [`Recorder::create`].
+#[derive(Default)]
+pub struct Recorder {
+    file: Option<BufWriter<File>>,
+    start: Option<Instant>,
+    wire_format: Option<WireFormat>,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, wire_format: WireFormat) -> Result<Self, crate::Error> {
+        let mut recorder = Self {
+            file: Some(BufWriter::new(File::create(path)?)),
+            start: Some(Instant::now()),
+            wire_format: None,
+        };
+
+        recorder.record_format(wire_format);
+
+        Ok(recorder)
+    }
+
+    /// Records the wire format if it changed, clients only learn theirs once connected.
+    pub fn record_format(&mut self, wire_format: WireFormat) {
+        if self.wire_format != Some(wire_format) {
+            self.wire_format = Some(wire_format);
+            self.record(&Record::Format(wire_format));
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.file.is_some()
+    }
+
+    pub fn record_incoming(&mut self, tick: NetworkTick, messages: &[Message]) {
+        for message in messages {
+            let record = Record::Incoming {
+                tick: tick.0,
+                time: self.time(),
+                sender: message.sender.clone(),
+                receiver: message.receiver.clone(),
+                payload: message.payload.clone(),
+            };
+
+            self.record(&record);
+        }
+    }
+
+    pub fn record_outgoing(&mut self, tick: NetworkTick, payloads: &[(NetworkTarget, Payload)]) {
+        for (target, payload) in payloads {
+            let record = Record::Outgoing {
+                tick: tick.0,
+                time: self.time(),
+                target: target.clone(),
+                payload: payload.clone(),
+            };
+
+            self.record(&record);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.flush() {
+                error!("Stopped recording: {:?}", e);
+                self.file = None;
+            }
+        }
+    }
+
+    fn record(&mut self, record: &Record) {
+        if let Some(file) = &mut self.file {
+            let result = serde_cbor::to_vec(record)
+                .map_err(crate::Error::from)
+                .and_then(|bytes| Ok(file.write_all(&encode_frame(&bytes))?));
+
+            if let Err(e) = result {
+                error!("Stopped recording: {:?}", e);
+                self.file = None;
+            }
+        }
+    }
+
+    fn time(&self) -> u64 {
+        self.start
+            .map(|start| start.elapsed().as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// Plays back the incoming messages of a recording in place of a connection.
+pub struct Replay {
+    wire_format: WireFormat,
+    records: Vec<Record>,
+    position: usize,
+    tick: u64,
+    paused: bool,
+    steps: u64,
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&std::fs::read(path)?);
+
+        let mut records = Vec::new();
+
+        while let Some(frame) = decoder.next_frame()? {
+            records.push(serde_cbor::from_slice(&frame)?);
+        }
+
+        let wire_format = match records.first() {
+            Some(Record::Format(wire_format)) => *wire_format,
+            _ => WireFormat::default(),
+        };
+
+        Ok(Self {
+            wire_format,
+            records,
+            position: 0,
+            tick: 0,
+            paused: false,
+            steps: 0,
+        })
+    }
+
+    pub fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advances a single tick while paused.
+    pub fn step(&mut self) {
+        self.steps += 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.records.len()
+    }
+
+    pub fn tick(&self) -> NetworkTick {
+        NetworkTick(self.tick)
+    }
+
+    /// Returns the messages received on the next tick, `None` while paused.
+    pub fn next_tick(&mut self) -> Option<Vec<Message>> {
+        if self.paused {
+            if self.steps == 0 {
+                return None;
+            }
+
+            self.steps -= 1;
+        }
+
+        self.tick += 1;
+
+        let mut messages = Vec::new();
+
+        while let Some(record) = self.records.get(self.position) {
+            match record {
+                Record::Incoming { tick, .. } | Record::Outgoing { tick, .. }
+                    if *tick > self.tick =>
+                {
+                    break
+                }
+                Record::Incoming {
+                    sender,
+                    receiver,
+                    payload,
+                    ..
+                } => messages.push(Message {
+                    payload: payload.clone(),
+                    sender: sender.clone(),
+                    receiver: receiver.clone(),
+                }),
+                Record::Format(wire_format) => self.wire_format = *wire_format,
+                Record::Outgoing { .. } => (),
+            }
+
+            self.position += 1;
+        }
+
+        Some(messages)
+    }
+}
+
+pub fn replay_system(
+    mut replay: ResMut<Replay>,
+    mut network_tick: ResMut<NetworkTick>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut network_log: ResMut<NetworkLog>,
+    mut message_events: ResMut<Events<Message>>,
+) {
+    if let Some(messages) = replay.next_tick() {
+        network_tick.0 = replay.tick().0;
+
+        if connection_manager.wire_format() != replay.wire_format() {
+            connection_manager.set_wire_format(replay.wire_format());
+        }
+
+        network_log.record_incoming(*network_tick, &messages);
+
+        for message in messages {
+            if connection_manager.get_local_actor().map(Actor::id) != Some(message.receiver.id()) {
+                connection_manager.set_local_actor_id(message.receiver.id());
+            }
+
+            message_events.send(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(id: u64) -> Message {
+        Message {
+            payload: Payload::Ping { id },
+            sender: Actor::new(ActorId(1), ActorTy::new::<Server>()),
+            receiver: Actor::new(ActorId(2), ActorTy::new::<Client>()),
+        }
+    }
+
+    fn ids(messages: Vec<Message>) -> Vec<u64> {
+        messages
+            .into_iter()
+            .map(|message| match message.payload {
+                Payload::Ping { id } => id,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    /// Records pings 1 and 2 on tick 1, nothing on tick 2 and ping 3 on tick 3.
+    fn replay(name: &str) -> Replay {
+        let path = std::env::temp_dir().join(format!("{}-{}.bin", name, std::process::id()));
+
+        let mut recorder = Recorder::create(&path, WireFormat::Bincode).unwrap();
+        recorder.record_incoming(NetworkTick(1), &[ping(1), ping(2)]);
+        recorder.record_outgoing(
+            NetworkTick(2),
+            &[(NetworkTarget::All, Payload::Pong { id: 1 })],
+        );
+        recorder.record_incoming(NetworkTick(3), &[ping(3)]);
+        recorder.flush();
+
+        let replay = Replay::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        replay
+    }
+
+    #[test]
+    fn recordings_play_back_incoming_messages_by_tick() {
+        let mut replay = replay("playback");
+
+        assert_eq!(replay.wire_format(), WireFormat::Bincode);
+        assert_eq!(ids(replay.next_tick().unwrap()), vec![1, 2]);
+        assert_eq!(ids(replay.next_tick().unwrap()), Vec::<u64>::new());
+        assert_eq!(ids(replay.next_tick().unwrap()), vec![3]);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn paused_replays_only_advance_by_steps() {
+        let mut replay = replay("paused");
+        replay.pause();
+
+        assert!(replay.next_tick().is_none());
+
+        replay.step();
+        assert_eq!(ids(replay.next_tick().unwrap()), vec![1, 2]);
+        assert!(replay.next_tick().is_none());
+        assert_eq!(replay.tick(), NetworkTick(1));
+    }
+
+    #[test]
+    fn replays_follow_later_formats() {
+        let path = std::env::temp_dir().join(format!("formats-{}.bin", std::process::id()));
+
+        let mut recorder = Recorder::create(&path, WireFormat::Cbor).unwrap();
+        recorder.record_format(WireFormat::Cbor);
+        recorder.record_format(WireFormat::Bincode);
+        recorder.record_incoming(NetworkTick(1), &[ping(1)]);
+        recorder.flush();
+
+        let mut replay = Replay::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(replay.wire_format(), WireFormat::Cbor);
+        assert_eq!(ids(replay.next_tick().unwrap()), vec![1]);
+        assert_eq!(replay.wire_format(), WireFormat::Bincode);
+        assert!(replay.is_finished());
+    }
+}
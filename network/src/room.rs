@@ -0,0 +1,154 @@
+use crate::*;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug)]
+pub enum RoomEvent {
+    Joined { room: String, actor_id: ActorId },
+    Left { room: String, actor_id: ActorId },
+}
+
+#[derive(Default)]
+pub struct Rooms {
+    rooms: HashMap<String, HashSet<ActorId>>,
+    events: Vec<RoomEvent>,
+}
+
+impl Rooms {
+    pub fn new() -> Self {
+        Self {
+            rooms: HashMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns false if the actor was already in the room.
+    pub fn join(&mut self, room: impl Into<String>, actor_id: ActorId) -> bool {
+        let room = room.into();
+
+        let joined = self
+            .rooms
+            .entry(room.clone())
+            .or_insert(HashSet::new())
+            .insert(actor_id);
+
+        if joined {
+            self.events.push(RoomEvent::Joined { room, actor_id });
+        }
+
+        joined
+    }
+
+    /// Returns false if the actor wasn't in the room.
+    pub fn leave(&mut self, room: &str, actor_id: ActorId) -> bool {
+        let left = if let Some(members) = self.rooms.get_mut(room) {
+            let left = members.remove(&actor_id);
+
+            if members.is_empty() {
+                self.rooms.remove(room);
+            }
+
+            left
+        } else {
+            false
+        };
+
+        if left {
+            self.events.push(RoomEvent::Left {
+                room: room.to_string(),
+                actor_id,
+            });
+        }
+
+        left
+    }
+
+    pub fn leave_all(&mut self, actor_id: ActorId) {
+        let rooms: Vec<_> = self.rooms_of(actor_id).cloned().collect();
+
+        for room in rooms {
+            self.leave(&room, actor_id);
+        }
+    }
+
+    pub fn contains(&self, room: &str, actor_id: ActorId) -> bool {
+        self.rooms
+            .get(room)
+            .map(|members| members.contains(&actor_id))
+            .unwrap_or(false)
+    }
+
+    pub fn members<'a>(&'a self, room: &str) -> impl Iterator<Item = &'a ActorId> {
+        self.rooms.get(room).into_iter().flatten()
+    }
+
+    pub fn rooms_of<'a>(&'a self, actor_id: ActorId) -> impl Iterator<Item = &'a String> {
+        self.rooms
+            .iter()
+            .filter(move |(_, members)| members.contains(&actor_id))
+            .map(|(room, _)| room)
+    }
+
+    pub fn rooms(&self) -> impl Iterator<Item = &String> {
+        self.rooms.keys()
+    }
+
+    pub fn drain_events(&mut self) -> Vec<RoomEvent> {
+        std::mem::replace(&mut self.events, Vec::new())
+    }
+}
+
+pub fn room_event_system(mut rooms: ResMut<Rooms>, mut room_events: ResMut<Events<RoomEvent>>) {
+    room_events.extend(rooms.drain_events().into_iter());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joining_and_leaving_report_changes_once() {
+        let mut rooms = Rooms::new();
+
+        assert!(rooms.join("lobby", ActorId(1)));
+        assert!(!rooms.join("lobby", ActorId(1)));
+        assert!(rooms.contains("lobby", ActorId(1)));
+
+        assert!(rooms.leave("lobby", ActorId(1)));
+        assert!(!rooms.leave("lobby", ActorId(1)));
+        assert!(!rooms.contains("lobby", ActorId(1)));
+
+        assert_eq!(rooms.drain_events().len(), 2);
+        assert!(rooms.drain_events().is_empty());
+    }
+
+    #[test]
+    fn empty_rooms_are_removed() {
+        let mut rooms = Rooms::new();
+        rooms.join("lobby", ActorId(1));
+        rooms.join("lobby", ActorId(2));
+
+        rooms.leave("lobby", ActorId(1));
+        assert_eq!(
+            rooms.members("lobby").collect::<Vec<_>>(),
+            vec![&ActorId(2)]
+        );
+
+        rooms.leave("lobby", ActorId(2));
+        assert_eq!(rooms.rooms().count(), 0);
+    }
+
+    #[test]
+    fn leave_all_only_removes_the_actor() {
+        let mut rooms = Rooms::new();
+        rooms.join("lobby", ActorId(1));
+        rooms.join("arena", ActorId(1));
+        rooms.join("arena", ActorId(2));
+
+        rooms.leave_all(ActorId(1));
+
+        assert_eq!(rooms.rooms_of(ActorId(1)).count(), 0);
+        assert!(rooms.contains("arena", ActorId(2)));
+        assert!(!rooms.rooms().any(|room| room == "lobby"));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::*;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Lets a client reclaim its [`ActorId`] after a brief disconnect.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct ResumeToken(pub [u8; 16]);
+
+impl ResumeToken {
+    pub fn generate() -> Self {
+        let mut token = [0u8; 16];
+        OsRng.fill_bytes(&mut token);
+        ResumeToken(token)
+    }
+}
+
+pub struct Session {
+    actor_id: ActorId,
+    player_id: Option<PlayerId>,
+    disconnected_at: Option<Instant>,
+}
+
+/// Sessions handed out to connected actors, kept around for a grace period after they
+/// disconnect. Clones share their sessions, so handshakes on other threads can resume them.
+#[derive(Clone, Default)]
+pub struct Sessions {
+    sessions: Arc<Mutex<HashMap<ResumeToken, Session>>>,
+}
+
+impl Sessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(
+        &mut self,
+        resume_token: ResumeToken,
+        actor_id: ActorId,
+        player_id: Option<PlayerId>,
+    ) {
+        self.sessions.lock().unwrap().insert(
+            resume_token,
+            Session {
+                actor_id,
+                player_id,
+                disconnected_at: None,
+            },
+        );
+    }
+
+    /// Inserts a session for an actor that hasn't connected yet, it expires like a disconnected
+    /// one.
+    pub fn hold(
+        &mut self,
+        resume_token: ResumeToken,
+        actor_id: ActorId,
+        player_id: Option<PlayerId>,
+    ) {
+        self.sessions.lock().unwrap().insert(
+            resume_token,
+            Session {
+                actor_id,
+                player_id,
+                disconnected_at: Some(Instant::now()),
+            },
+        );
+    }
+
+    /// Consumes the session of `resume_token`, a logged in player can only resume their own.
+    /// Sessions that are still connected can't be resumed.
+    pub fn resume(
+        &mut self,
+        resume_token: &ResumeToken,
+        player_id: Option<PlayerId>,
+    ) -> Option<ActorId> {
+        let mut sessions = self.sessions.lock().unwrap();
+
+        match sessions.get(resume_token) {
+            Some(session)
+                if session.player_id == player_id && session.disconnected_at.is_some() =>
+            {
+                sessions
+                    .remove(resume_token)
+                    .map(|session| session.actor_id)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn disconnect(&mut self, actor_id: ActorId) {
+        for session in self.sessions.lock().unwrap().values_mut() {
+            if session.actor_id == actor_id {
+                session.disconnected_at = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Forgets the session of `actor_id` so it can't be resumed.
+    pub fn remove(&mut self, actor_id: ActorId) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, session| session.actor_id != actor_id);
+    }
+
+    /// Whether `actor_id` is disconnected but can still be resumed.
+    pub fn is_held(&self, actor_id: ActorId) -> bool {
+        self.sessions
+            .lock()
+            .unwrap()
+            .values()
+            .any(|session| session.actor_id == actor_id && session.disconnected_at.is_some())
+    }
+
+    /// Forgets sessions that have been disconnected for longer than `grace_period`, returning
+    /// their actors.
+    pub fn expire(&mut self, grace_period: Duration) -> Vec<ActorId> {
+        let mut expired = Vec::new();
+
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, session| match session.disconnected_at {
+                Some(disconnected_at) if disconnected_at.elapsed() >= grace_period => {
+                    expired.push(session.actor_id);
+                    false
+                }
+                _ => true,
+            });
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn connected_sessions_never_expire() {
+        let mut sessions = Sessions::new();
+        sessions.insert(ResumeToken::generate(), ActorId(1), None);
+
+        assert!(!sessions.is_held(ActorId(1)));
+        assert!(sessions.expire(Duration::from_secs(0)).is_empty());
+    }
+
+    #[test]
+    fn disconnected_sessions_expire_after_the_grace_period() {
+        let mut sessions = Sessions::new();
+        let resume_token = ResumeToken::generate();
+        sessions.insert(resume_token, ActorId(1), None);
+        sessions.disconnect(ActorId(1));
+
+        assert!(sessions.is_held(ActorId(1)));
+        assert!(sessions.expire(Duration::from_millis(100)).is_empty());
+
+        thread::sleep(Duration::from_millis(150));
+
+        assert_eq!(
+            sessions.expire(Duration::from_millis(100)),
+            vec![ActorId(1)]
+        );
+        assert!(!sessions.is_held(ActorId(1)));
+        assert_eq!(sessions.resume(&resume_token, None), None);
+    }
+
+    #[test]
+    fn tokens_resume_once_and_only_for_their_player() {
+        let mut sessions = Sessions::new();
+        let resume_token = ResumeToken::generate();
+        sessions.insert(resume_token, ActorId(1), Some(PlayerId(7)));
+        sessions.disconnect(ActorId(1));
+
+        assert_eq!(sessions.resume(&resume_token, None), None);
+        assert_eq!(sessions.resume(&resume_token, Some(PlayerId(8))), None);
+        assert_eq!(
+            sessions.resume(&resume_token, Some(PlayerId(7))),
+            Some(ActorId(1))
+        );
+        assert_eq!(sessions.resume(&resume_token, Some(PlayerId(7))), None);
+    }
+
+    #[test]
+    fn connected_sessions_cant_be_resumed() {
+        let mut sessions = Sessions::new();
+        let resume_token = ResumeToken::generate();
+        sessions.insert(resume_token, ActorId(1), None);
+
+        assert_eq!(sessions.resume(&resume_token, None), None);
+
+        sessions.disconnect(ActorId(1));
+
+        assert_eq!(sessions.resume(&resume_token, None), Some(ActorId(1)));
+    }
+
+    #[test]
+    fn held_sessions_expire_like_disconnected_ones() {
+        let mut sessions = Sessions::new();
+        sessions.hold(ResumeToken::generate(), ActorId(1), None);
+
+        assert!(sessions.is_held(ActorId(1)));
+        assert_eq!(sessions.expire(Duration::from_secs(0)), vec![ActorId(1)]);
+    }
+}
@@ -1,29 +1,123 @@
 use crate::*;
+use std::{path::PathBuf, time::Duration};
 
 #[derive(Clone)]
 pub struct NetworkSettings {
     pub actor_ty: ActorTy,
 
+    /// How long a network tick and a [`stage::SIMULATION`] step are, both ends have to agree.
+    pub tick_duration: Duration,
+
     /// Sets all new connections type to this.
     pub connection_ty: ActorTy,
 
     pub sync_components_with: Vec<NetworkTarget>,
+
+    /// Compresses large outgoing frames if the other end supports it.
+    pub compression: Option<CompressionSettings>,
+
+    pub encryption: Encryption,
+
+    /// Proves who we are to ends that pin our [`IdentityKey`].
+    pub identity: Option<Identity>,
+
+    /// Refuses to connect unless the other end proves it holds this key, so nobody can sit in
+    /// the middle. Takes an encrypted connection.
+    pub server_key: Option<IdentityKey>,
+
+    /// Logs in with these after connecting.
+    pub credentials: Option<Credentials>,
+
+    /// Refuses connections that don't log in.
+    pub require_login: bool,
+
+    /// How long disconnected actors can be resumed for, `None` disables resuming.
+    pub resume_grace_period: Option<Duration>,
+
+    /// Simulates bad network conditions on outgoing payloads, for testing.
+    pub link_conditioner: Option<LinkConditioner>,
+
+    /// Records every message to this file, see [`Replay`].
+    pub record: Option<PathBuf>,
+
+    /// Clients use whatever format the server picked.
+    pub wire_format: WireFormat,
+
+    /// Limits what each connection can send us, `None` accepts everything. Connections of a
+    /// [peer type](Self::peer_tys) aren't limited.
+    pub inbound_budget: Option<InboundBudget>,
+
+    /// Disconnects connections that go silent for this long, see [`DisconnectReason::Timeout`].
+    pub timeout: Option<Duration>,
+
+    /// Retries lost or failed connections to the server, see [`ClientConnection`].
+    pub reconnect: Option<Reconnect>,
+
+    /// Actor types the other end can claim in its handshake, for links between servers. Peers
+    /// are left out of [`NetworkTarget::All`].
+    pub peer_tys: Vec<ActorTy>,
+
+    /// Proves a claimed [peer type](Self::peer_tys) in the handshake, either end refuses to
+    /// send or accept it over an unencrypted connection.
+    pub peer_secret: Option<String>,
+
+    /// Keeps the positions of networked entities this far back, see [`PositionHistory`].
+    pub lag_compensation: Option<Duration>,
+
+    /// Keeps this many of the last payloads in the [`NetworkLog`], for debugging.
+    pub log: Option<usize>,
 }
 
 impl NetworkSettings {
     pub fn server() -> Self {
         Self {
             actor_ty: ActorTy::new::<Server>(),
+            tick_duration: Duration::from_millis(50),
             connection_ty: ActorTy::new::<Client>(),
             sync_components_with: vec![NetworkTarget::ActorTy(ActorTy::new::<Client>())],
+            compression: Some(CompressionSettings::default()),
+            encryption: Encryption::Required,
+            identity: None,
+            server_key: None,
+            credentials: None,
+            require_login: false,
+            resume_grace_period: Some(Duration::from_secs(10)),
+            link_conditioner: None,
+            record: None,
+            wire_format: WireFormat::Cbor,
+            inbound_budget: Some(InboundBudget::default()),
+            timeout: Some(Duration::from_secs(15)),
+            reconnect: None,
+            peer_tys: Vec::new(),
+            peer_secret: None,
+            lag_compensation: Some(Duration::from_secs(1)),
+            log: None,
         }
     }
 
     pub fn client() -> Self {
         Self {
             actor_ty: ActorTy::new::<Client>(),
+            tick_duration: Duration::from_millis(50),
             connection_ty: ActorTy::new::<Server>(),
             sync_components_with: vec![NetworkTarget::ActorTy(ActorTy::new::<Server>())],
+            compression: Some(CompressionSettings::default()),
+            encryption: Encryption::Enabled,
+            identity: None,
+            server_key: None,
+            credentials: None,
+            require_login: false,
+            resume_grace_period: None,
+            link_conditioner: None,
+            record: None,
+            wire_format: WireFormat::Cbor,
+            inbound_budget: None,
+            timeout: Some(Duration::from_secs(15)),
+            reconnect: Some(Reconnect::default()),
+            peer_tys: Vec::new(),
+            peer_secret: None,
+            lag_compensation: None,
+            log: None,
         }
     }
 }
@@ -0,0 +1,135 @@
+use bevy::{ecs::ShouldRun, prelude::*};
+use std::time::Duration;
+
+/// Time as seen by [`stage::SIMULATION`](crate::stage::SIMULATION) systems, it advances by exactly
+/// one step per run so both ends simulate the same thing. Use it instead of [`Time`] in gameplay.
+#[derive(Clone, Debug)]
+pub struct SimTime {
+    step: Duration,
+    steps: u64,
+    accumulator: Duration,
+}
+
+impl SimTime {
+    pub fn new(step: Duration) -> Self {
+        Self {
+            step,
+            steps: 0,
+            accumulator: Duration::default(),
+        }
+    }
+
+    /// Adds `delta` of real time and takes a step if a whole one has built up, returning whether
+    /// it did. Call it again with no delta until it returns false to catch up.
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        self.accumulator += delta;
+
+        if self.accumulator < self.step {
+            return false;
+        }
+
+        self.accumulator -= self.step;
+        self.steps += 1;
+
+        true
+    }
+
+    /// Real time built up towards the next step.
+    pub fn accumulated(&self) -> Duration {
+        self.accumulator
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Steps simulated so far, including the current one.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.step.as_secs_f64() * self.steps as f64)
+    }
+}
+
+/// Run criteria of [`stage::NETWORK_RECEIVE`](crate::stage::NETWORK_RECEIVE), the first stage of
+/// a tick, it runs once for every step [`SimTime::tick`] takes.
+pub fn sim_time_run_criteria(
+    time: Res<Time>,
+    mut sim_time: ResMut<SimTime>,
+    mut looping: Local<bool>,
+) -> ShouldRun {
+    let delta = if *looping {
+        Duration::default()
+    } else {
+        time.delta()
+    };
+
+    *looping = sim_time.tick(delta);
+
+    if *looping {
+        ShouldRun::YesAndLoop
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Run criteria of the stages after [`stage::NETWORK_RECEIVE`](crate::stage::NETWORK_RECEIVE), they
+/// run once for every step they haven't yet, so each stage runs as often as the others.
+pub fn sim_step_run_criteria(sim_time: Res<SimTime>, mut steps: Local<u64>) -> ShouldRun {
+    if *steps < sim_time.steps() {
+        *steps += 1;
+        ShouldRun::YesAndLoop
+    } else {
+        ShouldRun::No
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_are_taken_once_a_whole_one_built_up() {
+        let mut sim_time = SimTime::new(Duration::from_millis(50));
+
+        assert!(!sim_time.tick(Duration::from_millis(30)));
+        assert!(sim_time.tick(Duration::from_millis(30)));
+        assert!(!sim_time.tick(Duration::default()));
+
+        assert_eq!(sim_time.steps(), 1);
+        assert_eq!(sim_time.accumulated(), Duration::from_millis(10));
+        assert_eq!(sim_time.elapsed(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn long_frames_catch_up_one_step_at_a_time() {
+        let mut sim_time = SimTime::new(Duration::from_millis(50));
+
+        assert!(sim_time.tick(Duration::from_millis(170)));
+        assert!(sim_time.tick(Duration::default()));
+        assert!(sim_time.tick(Duration::default()));
+        assert!(!sim_time.tick(Duration::default()));
+
+        assert_eq!(sim_time.steps(), 3);
+        assert_eq!(sim_time.accumulated(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn steps_advance_by_exactly_one_step() {
+        let mut sim_time = SimTime::new(Duration::from_millis(16));
+
+        for _ in 0..1000 {
+            sim_time.tick(Duration::from_millis(16));
+        }
+
+        assert_eq!(sim_time.steps(), 1000);
+        assert_eq!(sim_time.delta_seconds(), 0.016);
+        assert_eq!(sim_time.accumulated(), Duration::default());
+    }
+}
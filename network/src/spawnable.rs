@@ -5,14 +5,14 @@ use std::collections::{HashMap, HashSet};
 #[derive(Default)]
 pub struct SpawnManager {
     spawnables: HashMap<NetworkEntity, (NetworkTarget, Payload)>,
-    connections: HashMap<ConnectionId, HashSet<NetworkEntity>>,
+    actors: HashMap<ActorId, HashSet<NetworkEntity>>,
 }
 
 impl SpawnManager {
     pub fn new() -> Self {
         Self {
             spawnables: HashMap::new(),
-            connections: HashMap::new(),
+            actors: HashMap::new(),
         }
     }
 
@@ -25,20 +25,20 @@ impl SpawnManager {
         self.spawnables.insert(network_entity, (target, payload));
     }
 
-    pub fn confirm_spawn(&mut self, connection_id: ConnectionId, network_entity: NetworkEntity) {
-        self.connections
-            .entry(connection_id)
+    pub fn confirm_spawn(&mut self, actor_id: ActorId, network_entity: NetworkEntity) {
+        self.actors
+            .entry(actor_id)
             .or_insert(HashSet::new())
             .insert(network_entity);
     }
 
     pub fn get_not_spawned(
         &self,
-        connection_id: ConnectionId,
+        actor_id: ActorId,
     ) -> Vec<(NetworkEntity, NetworkTarget, Payload)> {
         let mut not_spawned = Vec::new();
 
-        if let Some(spawned) = self.connections.get(&connection_id) {
+        if let Some(spawned) = self.actors.get(&actor_id) {
             for (network_entity, (target, payload)) in &self.spawnables {
                 if !spawned.contains(network_entity) {
                     not_spawned.push((*network_entity, target.clone(), payload.clone()));
@@ -48,6 +48,22 @@ impl SpawnManager {
 
         not_spawned
     }
+
+    /// Stops spawning `network_entity` for new actors, returns its spawn payload.
+    pub fn unregister_spawn(&mut self, network_entity: NetworkEntity) -> Option<Payload> {
+        for spawned in self.actors.values_mut() {
+            spawned.remove(&network_entity);
+        }
+
+        self.spawnables
+            .remove(&network_entity)
+            .map(|(_, payload)| payload)
+    }
+
+    /// Forgets what `actor_id` has been sent, it won't be coming back.
+    pub fn forget(&mut self, actor_id: ActorId) {
+        self.actors.remove(&actor_id);
+    }
 }
 
 #[derive(Bundle)]
@@ -83,7 +99,7 @@ impl SpawnContext {
 }
 
 #[typetag::serde]
-pub trait Spawnable {
+pub trait Spawnable: Send + Sync {
     fn spawn(
         &self,
         commands: &mut Commands,
@@ -91,6 +107,10 @@ pub trait Spawnable {
         ctx: &SpawnContext,
         bundle: SpawnBundle,
     ) -> Entity;
+
+    /// Called before the entity is spawned on the server of another zone, where its owner
+    /// connects as `actor_id`.
+    fn set_owner(&mut self, _actor_id: ActorId) {}
 }
 
 #[derive(Default)]
@@ -104,6 +124,7 @@ pub fn spawn_system(world: &mut World, resources: &mut Resources) {
 
     {
         let mut network_entity_registry = resources.get_mut::<NetworkEntityRegistry>().unwrap();
+        let wire_format = resources.get::<ConnectionManager>().unwrap().wire_format();
         let mut event_reader = resources.get_mut::<SpawnSystemEventReader>().unwrap();
         let events = resources.get::<Events<Message>>().unwrap();
 
@@ -113,7 +134,16 @@ pub fn spawn_system(world: &mut World, resources: &mut Resources) {
                 data,
             } = &message.payload
             {
-                let spawnable: Box<dyn Spawnable> = serde_cbor::from_slice(data).unwrap();
+                let spawnable: Box<dyn Spawnable> = match wire_format.decode(data) {
+                    Ok(spawnable) => spawnable,
+                    Err(e) => {
+                        error!(
+                            "Failed to decode a spawn from {:?}: {:?}",
+                            message.sender, e
+                        );
+                        continue;
+                    }
+                };
 
                 let context = SpawnContext::new(message.receiver.clone(), message.sender.clone());
 
@@ -123,9 +153,10 @@ pub fn spawn_system(world: &mut World, resources: &mut Resources) {
 
                 let entity = spawnable.spawn(&mut commands, resources, &context, bundle);
 
-                network_entity_registry
-                    .insert(*network_entity, entity)
-                    .unwrap();
+                if let Err(e) = network_entity_registry.insert(*network_entity, entity) {
+                    error!("Failed to spawn {:?}: {:?}", network_entity, e);
+                    commands.despawn(entity);
+                }
             }
         }
     }
@@ -137,30 +168,27 @@ pub fn spawn_detection_system(
     connection_manager: Res<ConnectionManager>,
     mut spawn_manager: ResMut<SpawnManager>,
     mut network_handle: ResMut<NetworkHandle>,
+    rooms: Res<Rooms>,
 ) {
     // TODO: optimize
-    for (connection_id, _) in connection_manager.connections() {
-        if !spawn_manager.connections.contains_key(connection_id) {
+    for (_, connection) in connection_manager.connections() {
+        if !spawn_manager.actors.contains_key(&connection.actor().id()) {
             spawn_manager
-                .connections
-                .insert(*connection_id, HashSet::new());
+                .actors
+                .insert(connection.actor().id(), HashSet::new());
         }
     }
 
-    let SpawnManager {
-        spawnables,
-        connections,
-    } = &mut *spawn_manager;
+    let SpawnManager { spawnables, actors } = &mut *spawn_manager;
 
     for (network_id, (target, payload)) in spawnables {
-        for connection_id in connection_manager.get_targeted_connection_ids(target) {
-            let actor_id = connection_manager.get_actor(connection_id).unwrap().id();
-            let spawned = &mut connections.get_mut(&connection_id).unwrap();
+        for actor_id in connection_manager.get_targeted_actor_ids(target, &*rooms) {
+            let spawned = &mut actors.get_mut(&actor_id).unwrap();
 
             if !spawned.contains(network_id) {
                 info!(
-                    "Connection {:?}, doesn't have a copy of {:?}",
-                    connection_id, network_id
+                    "Actor {:?}, doesn't have a copy of {:?}",
+                    actor_id, network_id
                 );
 
                 spawned.insert(*network_id);
@@ -0,0 +1,122 @@
+use crate::*;
+use bevy::reflect::Uuid;
+use std::{collections::HashMap, time::Duration};
+
+/// What [`ConnectionStatistics`] groups payloads by.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PayloadKind {
+    Component(Uuid),
+    Spawn,
+    Ping,
+    Admin,
+    Goodbye,
+    Zone,
+}
+
+impl PayloadKind {
+    pub fn of(payload: &Payload) -> Self {
+        match payload {
+            Payload::ComponentUpdate {
+                network_type_uuid, ..
+            } => PayloadKind::Component(*network_type_uuid),
+            Payload::Spawn { .. } => PayloadKind::Spawn,
+            Payload::Ping { .. } | Payload::Pong { .. } => PayloadKind::Ping,
+            Payload::Admin(_) => PayloadKind::Admin,
+            Payload::Goodbye(_) => PayloadKind::Goodbye,
+            Payload::Zone(_) => PayloadKind::Zone,
+        }
+    }
+
+    /// The synced component's type name, otherwise the kind of payload.
+    pub fn name(&self, network_type_registry: &NetworkTypeRegistry) -> String {
+        match self {
+            PayloadKind::Component(uuid) => network_type_registry
+                .get_name(uuid)
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| uuid.to_string()),
+            kind => format!("{:?}", kind),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PayloadCounts {
+    pub sent: u64,
+    pub received: u64,
+    /// Bytes of payload data, not counting the payload's own encoding.
+    pub data_bytes_sent: u64,
+    pub data_bytes_received: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionStatistics {
+    /// Bytes written to the socket, including frame headers.
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Bytes of serialized payloads before compression.
+    pub uncompressed_bytes_sent: u64,
+    pub uncompressed_bytes_received: u64,
+    pub frames_sent: u64,
+    pub frames_received: u64,
+    pub compressed_frames_sent: u64,
+    pub payloads_sent: u64,
+    pub payloads_received: u64,
+    pub payload_kinds: HashMap<PayloadKind, PayloadCounts>,
+    /// Received frames that didn't fit the [`InboundBudget`], dropped without being decoded.
+    pub frames_dropped: u64,
+    /// Received payloads that didn't fit the [`InboundBudget`].
+    pub payloads_dropped: u64,
+    pub data_bytes_dropped: u64,
+    /// Smoothed over the last few pings, `None` until the first pong arrives.
+    pub round_trip_time: Option<Duration>,
+}
+
+impl ConnectionStatistics {
+    /// Uncompressed payload bytes per byte on the wire, frame headers make this dip slightly
+    /// below 1.0 when nothing is compressed.
+    pub fn compression_ratio(&self) -> f32 {
+        if self.bytes_sent == 0 {
+            1.0
+        } else {
+            self.uncompressed_bytes_sent as f32 / self.bytes_sent as f32
+        }
+    }
+
+    pub fn count_sent(&mut self, payload: &Payload) {
+        let counts = self
+            .payload_kinds
+            .entry(PayloadKind::of(payload))
+            .or_insert(PayloadCounts::default());
+
+        counts.sent += 1;
+        counts.data_bytes_sent += data_len(payload) as u64;
+    }
+
+    pub fn count_received(&mut self, payload: &Payload) {
+        let counts = self
+            .payload_kinds
+            .entry(PayloadKind::of(payload))
+            .or_insert(PayloadCounts::default());
+
+        counts.received += 1;
+        counts.data_bytes_received += data_len(payload) as u64;
+    }
+
+    pub fn add_round_trip_time(&mut self, sample: Duration) {
+        self.round_trip_time = Some(match self.round_trip_time {
+            Some(round_trip_time) => (round_trip_time * 7 + sample) / 8,
+            None => sample,
+        });
+    }
+}
+
+pub(crate) fn data_len(payload: &Payload) -> usize {
+    match payload {
+        Payload::ComponentUpdate { data, .. } | Payload::Spawn { data, .. } => data.len(),
+        Payload::Ping { .. } | Payload::Pong { .. } | Payload::Goodbye(_) => 0,
+        Payload::Admin(AdminPayload::Command(text))
+        | Payload::Admin(AdminPayload::Response(text))
+        | Payload::Admin(AdminPayload::Broadcast(text)) => text.len(),
+        Payload::Zone(zone_payload) => zone_payload.data_len(),
+    }
+}
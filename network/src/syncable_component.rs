@@ -1,21 +1,117 @@
 use crate::*;
-use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy::{
+    prelude::*,
+    reflect::{
+        serde::{ReflectDeserializer, ReflectSerializer},
+        ReflectRef, TypeRegistry,
+    },
+};
+use serde::de::DeserializeSeed;
 
-pub trait SyncableComponent: NetworkTypeUuid {
-    fn from_bytes(bytes: &[u8], type_registry: &TypeRegistry) -> Self;
-    fn to_bytes(&self, type_registry: &TypeRegistry) -> Vec<u8>;
+pub trait SyncableComponent: NetworkTypeUuid + Sized {
+    /// The bytes come from the other end, anything they decode to has to be checked.
+    fn from_bytes(
+        bytes: &[u8],
+        type_registry: &TypeRegistry,
+        wire_format: WireFormat,
+    ) -> Result<Self, crate::Error>;
+    fn to_bytes(&self, type_registry: &TypeRegistry, wire_format: WireFormat) -> Vec<u8>;
+}
+
+/// Decodes a component synced with `reflect`. Anything that doesn't fit `T` is an
+/// [`Error::InvalidComponent`], applying it would panic.
+pub fn reflect_from_bytes<T: Reflect + Default>(
+    bytes: &[u8],
+    type_registry: &TypeRegistry,
+) -> Result<T, crate::Error> {
+    let type_registry = type_registry.read();
+
+    let reflect_deserializer = ReflectDeserializer::new(&type_registry);
+    let mut deserializer = serde_cbor::Deserializer::from_slice(bytes);
+    let reflect_value = reflect_deserializer.deserialize(&mut deserializer)?;
+
+    let mut value = T::default();
+
+    if !can_apply(&*reflect_value, &value) {
+        return Err(crate::Error::InvalidComponent);
+    }
+
+    value.apply(&*reflect_value);
+
+    Ok(value)
+}
+
+/// Whether `target.apply(value)` goes through without panicking. Structs need the same type
+/// name and fields that fit, values the same type. Lists and maps are only checked against what
+/// `target` already holds, ones that would grow it are refused.
+fn can_apply(value: &dyn Reflect, target: &dyn Reflect) -> bool {
+    match (value.reflect_ref(), target.reflect_ref()) {
+        (ReflectRef::Struct(value), ReflectRef::Struct(target)) => {
+            value.type_name() == target.type_name()
+                && (0..value.field_len()).all(|i| match (value.name_at(i), value.field_at(i)) {
+                    // fields the target doesn't have are skipped by `apply`
+                    (Some(name), Some(field)) => target
+                        .field(name)
+                        .map(|target_field| can_apply(field, target_field))
+                        .unwrap_or(true),
+                    _ => false,
+                })
+        }
+        (ReflectRef::TupleStruct(value), ReflectRef::TupleStruct(target)) => {
+            value.type_name() == target.type_name()
+                && (0..value.field_len()).all(|i| match (value.field(i), target.field(i)) {
+                    (Some(field), Some(target_field)) => can_apply(field, target_field),
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                })
+        }
+        (ReflectRef::List(value), ReflectRef::List(target)) => {
+            value.len() <= target.len()
+                && (0..value.len()).all(|i| match (value.get(i), target.get(i)) {
+                    (Some(element), Some(target_element)) => can_apply(element, target_element),
+                    _ => false,
+                })
+        }
+        (ReflectRef::Map(value), ReflectRef::Map(target)) => {
+            (0..value.len()).all(|i| match value.get_at(i) {
+                Some((key, element)) => target
+                    .get(key)
+                    .map(|target_element| can_apply(element, target_element))
+                    .unwrap_or(false),
+                None => false,
+            })
+        }
+        (ReflectRef::Value(value), ReflectRef::Value(target)) => {
+            value.any().type_id() == target.any().type_id()
+        }
+        _ => false,
+    }
+}
+
+pub fn reflect_to_bytes(value: &dyn Reflect, type_registry: &TypeRegistry) -> Vec<u8> {
+    let type_registry = type_registry.read();
+    let serializer = ReflectSerializer::new(value, &type_registry);
+    serde_cbor::to_vec(&serializer).unwrap()
 }
 
 #[macro_export]
 macro_rules! serde_sync {
     ($ident:path) => {
         impl SyncableComponent for $ident {
-            fn from_bytes(bytes: &[u8], _type_registry: &bevy::reflect::TypeRegistry) -> Self {
-                serde_cbor::from_slice(bytes).unwrap()
+            fn from_bytes(
+                bytes: &[u8],
+                _type_registry: &bevy::reflect::TypeRegistry,
+                wire_format: WireFormat,
+            ) -> std::result::Result<Self, $crate::Error> {
+                wire_format.decode(bytes)
             }
 
-            fn to_bytes(&self, _type_registry: &bevy::reflect::TypeRegistry) -> Vec<u8> {
-                serde_cbor::to_vec(self).unwrap()
+            fn to_bytes(
+                &self,
+                _type_registry: &bevy::reflect::TypeRegistry,
+                wire_format: WireFormat,
+            ) -> Vec<u8> {
+                wire_format.encode(self).unwrap()
             }
         }
     };
@@ -30,33 +126,63 @@ macro_rules! serde_sync {
 macro_rules! reflect_sync {
     ($ident:path) => {
         impl SyncableComponent for $ident {
-            fn from_bytes(bytes: &[u8], type_registry: &bevy::reflect::TypeRegistry) -> Self {
-                use serde::de::DeserializeSeed;
-
-                let type_registry = type_registry.read();
+            fn from_bytes(
+                bytes: &[u8],
+                type_registry: &bevy::reflect::TypeRegistry,
+                _wire_format: WireFormat,
+            ) -> std::result::Result<Self, $crate::Error> {
+                $crate::reflect_from_bytes(bytes, type_registry)
+            }
 
-                let reflect_deserializer =
-                    bevy::reflect::serde::ReflectDeserializer::new(&type_registry);
-                let mut deserializer = serde_cbor::Deserializer::from_slice(bytes);
-                let reflect_value = reflect_deserializer.deserialize(&mut deserializer).unwrap();
+            fn to_bytes(
+                &self,
+                type_registry: &bevy::reflect::TypeRegistry,
+                _wire_format: WireFormat,
+            ) -> Vec<u8> {
+                $crate::reflect_to_bytes(self, type_registry)
+            }
+        }
+    };
 
-                let mut value = Self::default();
+    ($ident:path = $uuid:expr) => {
+        reflect_sync!($ident);
+        network_uuid!($ident = $uuid);
+    };
+}
 
-                value.apply(&*reflect_value);
+/// A component that packs itself with a [`BitWriter`], see [`compact_sync!`].
+pub trait CompactComponent: Sized {
+    fn write(&self, writer: &mut BitWriter);
+    fn read(reader: &mut BitReader) -> Result<Self, crate::Error>;
+}
 
-                value
+/// Syncs a [`CompactComponent`], the encoding is the same for every wire format.
+#[macro_export]
+macro_rules! compact_sync {
+    ($ident:path) => {
+        impl SyncableComponent for $ident {
+            fn from_bytes(
+                bytes: &[u8],
+                _type_registry: &bevy::reflect::TypeRegistry,
+                _wire_format: WireFormat,
+            ) -> std::result::Result<Self, $crate::Error> {
+                <Self as CompactComponent>::read(&mut BitReader::new(bytes))
             }
 
-            fn to_bytes(&self, type_registry: &bevy::reflect::TypeRegistry) -> Vec<u8> {
-                let type_registry = type_registry.read();
-                let serializer = bevy::reflect::serde::ReflectSerializer::new(self, &type_registry);
-                serde_cbor::to_vec(&serializer).unwrap()
+            fn to_bytes(
+                &self,
+                _type_registry: &bevy::reflect::TypeRegistry,
+                _wire_format: WireFormat,
+            ) -> Vec<u8> {
+                let mut writer = BitWriter::new();
+                CompactComponent::write(self, &mut writer);
+                writer.finish()
             }
         }
     };
 
     ($ident:path = $uuid:expr) => {
-        reflect_sync!($ident);
+        compact_sync!($ident);
         network_uuid!($ident = $uuid);
     };
 }
@@ -0,0 +1,201 @@
+use crate::*;
+use bevy::{ecs::Component, prelude::*};
+use std::{
+    fmt::Debug,
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+/// A headless server and clients connected over localhost, for tests.
+pub struct TestNetwork {
+    server: App,
+    clients: Vec<App>,
+}
+
+impl TestNetwork {
+    /// Connects `clients` clients, `setup` is called on every app after the network plugin is
+    /// added.
+    pub fn new(clients: usize, setup: impl Fn(&mut AppBuilder)) -> Self {
+        Self::with_settings(
+            clients,
+            NetworkSettings::server(),
+            NetworkSettings::client(),
+            setup,
+        )
+    }
+
+    pub fn with_settings(
+        clients: usize,
+        server_settings: NetworkSettings,
+        client_settings: NetworkSettings,
+        setup: impl Fn(&mut AppBuilder),
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut server = App::build();
+        server
+            .add_plugins(MinimalPlugins)
+            .add_plugin(NetworkPlugin::server(listener).with_settings(server_settings));
+        setup(&mut server);
+
+        let mut network = Self {
+            server: server.app,
+            clients: Vec::new(),
+        };
+
+        for _ in 0..clients {
+            let settings = client_settings.clone();
+            let (sender, receiver) = mpsc::channel();
+
+            // the handshake blocks, so the server has to keep updating while it runs
+            thread::spawn(move || {
+                let mut connection_manager = ConnectionManager::new(settings.actor_ty);
+                connection_manager.set_wire_format(settings.wire_format);
+
+                let result = connection_manager
+                    .add_connection(
+                        TcpStream::connect(addr).unwrap(),
+                        settings.connection_ty,
+                        Handshake::new(HandshakeActorIds::None, &settings),
+                        &settings,
+                        None,
+                    )
+                    .map(|_| connection_manager);
+
+                sender.send(result).unwrap();
+            });
+
+            let connection_manager = loop {
+                network.server.update();
+
+                match receiver.try_recv() {
+                    Ok(result) => break result.expect("Failed to connect"),
+                    Err(TryRecvError::Empty) => thread::sleep(Duration::from_millis(1)),
+                    Err(TryRecvError::Disconnected) => panic!("Client failed to connect"),
+                }
+            };
+
+            let mut client = App::build();
+            client.add_plugins(MinimalPlugins).add_plugin(
+                NetworkPlugin::connected(connection_manager).with_settings(client_settings.clone()),
+            );
+            setup(&mut client);
+
+            network.clients.push(client.app);
+        }
+
+        network
+    }
+
+    pub fn server(&self) -> &App {
+        &self.server
+    }
+
+    pub fn server_mut(&mut self) -> &mut App {
+        &mut self.server
+    }
+
+    pub fn client(&self, index: usize) -> &App {
+        &self.clients[index]
+    }
+
+    pub fn client_mut(&mut self, index: usize) -> &mut App {
+        &mut self.clients[index]
+    }
+
+    pub fn clients(&self) -> &[App] {
+        &self.clients
+    }
+
+    /// Runs every app until it has advanced at least one network tick, server first.
+    pub fn step(&mut self) {
+        step_app(&mut self.server);
+
+        for client in &mut self.clients {
+            step_app(client);
+        }
+    }
+
+    pub fn step_ticks(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Steps until `condition` holds, returns false if it didn't within `max_ticks`.
+    pub fn run_until(&mut self, max_ticks: usize, condition: impl Fn(&Self) -> bool) -> bool {
+        for _ in 0..max_ticks {
+            if condition(self) {
+                return true;
+            }
+
+            self.step();
+        }
+
+        condition(self)
+    }
+}
+
+fn step_app(app: &mut App) {
+    let tick = app.resources.get::<NetworkTick>().unwrap().0;
+
+    loop {
+        app.update();
+
+        if app.resources.get::<NetworkTick>().unwrap().0 != tick {
+            return;
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Looks up networked entities in an app, see [`TestNetwork`].
+pub trait TestAppExt {
+    fn network_entity(&self, network_entity: NetworkEntity) -> Option<Entity>;
+
+    fn network_component<T: Component + Clone>(&self, network_entity: NetworkEntity) -> Option<T>;
+
+    /// Panics unless `network_entity` exists and has a `T` equal to `expected`.
+    fn assert_network_component<T: Component + Clone + PartialEq + Debug>(
+        &self,
+        network_entity: NetworkEntity,
+        expected: &T,
+    );
+}
+
+impl TestAppExt for App {
+    fn network_entity(&self, network_entity: NetworkEntity) -> Option<Entity> {
+        self.resources
+            .get::<NetworkEntityRegistry>()
+            .unwrap()
+            .get(&network_entity)
+            .copied()
+    }
+
+    fn network_component<T: Component + Clone>(&self, network_entity: NetworkEntity) -> Option<T> {
+        let entity = self.network_entity(network_entity)?;
+
+        self.world
+            .get::<T>(entity)
+            .ok()
+            .map(|component| component.clone())
+    }
+
+    fn assert_network_component<T: Component + Clone + PartialEq + Debug>(
+        &self,
+        network_entity: NetworkEntity,
+        expected: &T,
+    ) {
+        match self.network_component::<T>(network_entity) {
+            Some(component) => assert_eq!(&component, expected, "{:?}", network_entity),
+            None if self.network_entity(network_entity).is_some() => {
+                panic!("{:?} has no {}", network_entity, std::any::type_name::<T>())
+            }
+            None => panic!("{:?} doesn't exist", network_entity),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How payloads and their data are serialized, decided by whoever assigns actor ids.
+///
+/// Components synced with `reflect` are always CBOR since they need a self describing format.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum WireFormat {
+    /// Self describing, easier to debug.
+    Cbor,
+    /// Compact, for production.
+    Bincode,
+}
+
+impl Default for WireFormat {
+    fn default() -> Self {
+        WireFormat::Cbor
+    }
+}
+
+impl WireFormat {
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, crate::Error> {
+        match self {
+            WireFormat::Cbor => Ok(serde_cbor::to_vec(value)?),
+            WireFormat::Bincode => Ok(bincode::serialize(value)?),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, crate::Error> {
+        match self {
+            WireFormat::Cbor => Ok(serde_cbor::from_slice(bytes)?),
+            WireFormat::Bincode => Ok(bincode::deserialize(bytes)?),
+        }
+    }
+}
+
+impl FromStr for WireFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cbor" => Ok(WireFormat::Cbor),
+            "bincode" => Ok(WireFormat::Bincode),
+            _ => Err(format!(
+                "unknown wire format {}, expected cbor or bincode",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn wire_formats_parse_from_their_names() {
+        assert_eq!("cbor".parse(), Ok(WireFormat::Cbor));
+        assert_eq!("bincode".parse(), Ok(WireFormat::Bincode));
+        assert!("json".parse::<WireFormat>().is_err());
+    }
+
+    #[test]
+    fn both_formats_round_trip_and_bincode_is_smaller() {
+        let payloads = vec![Payload::Spawn {
+            network_entity: NetworkEntity(7),
+            data: vec![1, 2, 3],
+        }];
+
+        let cbor = WireFormat::Cbor.encode(&payloads).unwrap();
+        let bincode = WireFormat::Bincode.encode(&payloads).unwrap();
+
+        assert_eq!(
+            WireFormat::Cbor.decode::<Vec<Payload>>(&cbor).unwrap(),
+            payloads
+        );
+        assert_eq!(
+            WireFormat::Bincode
+                .decode::<Vec<Payload>>(&bincode)
+                .unwrap(),
+            payloads
+        );
+        assert!(bincode.len() < cbor.len());
+    }
+
+    #[test]
+    fn formats_dont_read_each_other() {
+        let bincode = WireFormat::Bincode
+            .encode(&Payload::Ping { id: 7 })
+            .unwrap();
+
+        assert!(WireFormat::Cbor.decode::<Payload>(&bincode).is_err());
+    }
+}
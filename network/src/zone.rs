@@ -0,0 +1,700 @@
+use crate::*;
+use bevy::{prelude::*, reflect::Uuid};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The actor type zone servers claim on their links to each other, proven with the zone secret.
+#[derive(NetworkTypeUuid)]
+#[uuid = "00000000-0000-018e-f04f-f14a89580b56"]
+pub struct ZoneServer;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct ZoneId(pub u32);
+
+/// A rectangle of the world and the server that simulates it.
+#[derive(Clone, Debug)]
+pub struct Zone {
+    pub id: ZoneId,
+    pub min: Vec2,
+    pub max: Vec2,
+    pub addr: String,
+}
+
+impl Zone {
+    pub fn contains(&self, position: Vec2) -> bool {
+        self.contains_within(position, 0.0)
+    }
+
+    /// Whether `position` is in the zone grown by `margin` on every side.
+    pub fn contains_within(&self, position: Vec2, margin: f32) -> bool {
+        position.x >= self.min.x - margin
+            && position.x < self.max.x + margin
+            && position.y >= self.min.y - margin
+            && position.y < self.max.y + margin
+    }
+}
+
+/// `id:min_x,min_y,max_x,max_y@addr`, like `1:0,-500,1000,500@127.0.0.1:9001`.
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("expected id:min_x,min_y,max_x,max_y@addr, got {}", s);
+
+        let (id, rest) = s.split_at(s.find(':').ok_or_else(error)?);
+        let (bounds, addr) = rest[1..].split_at(rest.find('@').ok_or_else(error)? - 1);
+
+        let bounds = bounds
+            .split(',')
+            .map(|bound| bound.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error())?;
+
+        if bounds.len() != 4 || addr.len() < 2 {
+            return Err(error());
+        }
+
+        Ok(Self {
+            id: ZoneId(id.trim().parse().map_err(|_| error())?),
+            min: Vec2::new(bounds[0], bounds[1]),
+            max: Vec2::new(bounds[2], bounds[3]),
+            addr: addr[1..].to_string(),
+        })
+    }
+}
+
+/// How the world is split up, the same on every zone server. Players keep their `PlayerId`
+/// across zones only if every zone server shares its accounts.
+#[derive(Clone)]
+pub struct ZoneSettings {
+    pub zones: Vec<Zone>,
+    /// The zone this server simulates.
+    pub local: ZoneId,
+    /// Links prove they are zone servers with this in their handshake, see
+    /// [`NetworkSettings::peer_secret`].
+    pub secret: String,
+    /// Logs our links in, for zone servers that require it.
+    pub credentials: Option<Credentials>,
+    /// How long to wait before reconnecting a lost link.
+    pub retry_delay: Duration,
+    /// How far past the edge of the local zone entities get before they're handed off, so one
+    /// moving along the edge doesn't bounce between servers.
+    pub margin: f32,
+}
+
+impl ZoneSettings {
+    pub fn new(zones: Vec<Zone>, local: ZoneId, secret: impl Into<String>) -> Self {
+        Self {
+            zones,
+            local,
+            secret: secret.into(),
+            credentials: None,
+            retry_delay: Duration::from_secs(3),
+            margin: 32.0,
+        }
+    }
+
+    pub fn get(&self, zone_id: ZoneId) -> Option<&Zone> {
+        self.zones.iter().find(|zone| zone.id == zone_id)
+    }
+
+    pub fn zone_at(&self, position: Vec2) -> Option<&Zone> {
+        self.zones.iter().find(|zone| zone.contains(position))
+    }
+
+    /// The zone an entity of the local zone at `position` should be handed off to.
+    pub fn handoff_zone(&self, position: Vec2) -> Option<&Zone> {
+        let local = self.get(self.local)?;
+
+        if local.contains_within(position, self.margin) {
+            return None;
+        }
+
+        self.zone_at(position)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ZonePayload {
+    /// The first payload over a link between zone servers, the secret was already checked in
+    /// the handshake.
+    Hello {
+        zone: ZoneId,
+    },
+    Handoff(Handoff),
+    /// The entity moved on to the server of another zone.
+    Left(NetworkEntity),
+    /// Sent to the owner of an entity that moved on, right before it's disconnected.
+    Redirect {
+        addr: String,
+        resume_token: ResumeToken,
+    },
+}
+
+impl ZonePayload {
+    pub(crate) fn data_len(&self) -> usize {
+        match self {
+            ZonePayload::Hello { .. } => 0,
+            ZonePayload::Handoff(handoff) => {
+                handoff.spawnable.len()
+                    + handoff
+                        .components
+                        .iter()
+                        .map(|(_, data)| data.len())
+                        .sum::<usize>()
+            }
+            ZonePayload::Left(_) => 0,
+            ZonePayload::Redirect { addr, .. } => addr.len(),
+        }
+    }
+}
+
+/// An entity crossing over to the server of another zone.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    /// An encoded `Box<dyn Spawnable>`.
+    pub spawnable: Vec<u8>,
+    /// Every synced component the entity had.
+    pub components: Vec<(Uuid, Vec<u8>)>,
+    /// The owner resumes with the token, and gets the entity handed to it again.
+    pub owner: Option<(ResumeToken, Option<PlayerId>)>,
+}
+
+/// Moves to the server of whatever zone its `Transform` ends up in, with its owner.
+pub struct ZoneEntity {
+    pub owner: Option<ActorId>,
+}
+
+enum ZoneLink {
+    Connecting(Mutex<mpsc::Receiver<Progress>>),
+    Connected(ActorId),
+    Down { retry_at: Instant },
+}
+
+#[derive(Default)]
+pub struct ZoneLinks {
+    /// Our links to the other zone servers.
+    links: HashMap<ZoneId, ZoneLink>,
+    /// Links from other zone servers that said hello with the right secret.
+    verified: HashMap<ActorId, ZoneId>,
+    /// Handed over entities waiting to be spawned, to get their components back.
+    restores: Vec<(NetworkEntity, Vec<(Uuid, Vec<u8>)>)>,
+}
+
+impl ZoneLinks {
+    /// The actor of our link to `zone_id`, if it's up.
+    pub fn link(&self, zone_id: ZoneId) -> Option<ActorId> {
+        match self.links.get(&zone_id) {
+            Some(ZoneLink::Connected(actor_id)) => Some(*actor_id),
+            _ => None,
+        }
+    }
+}
+
+/// Links zone servers to each other and hands off [`ZoneEntity`]s crossing into another zone.
+/// Needs a listening [`NetworkPlugin`] with the [`Identity`] every zone server shares, clients
+/// follow their entity with [`ZonePayload::Redirect`].
+pub struct ZonePlugin {
+    pub settings: ZoneSettings,
+}
+
+impl Plugin for ZonePlugin {
+    fn build(&self, app_builder: &mut AppBuilder) {
+        {
+            let resources = app_builder.resources();
+            let mut network_settings = resources.get_mut::<NetworkSettings>().unwrap();
+
+            if !network_settings
+                .peer_tys
+                .contains(&ActorTy::new::<ZoneServer>())
+            {
+                network_settings.peer_tys.push(ActorTy::new::<ZoneServer>());
+            }
+
+            network_settings.peer_secret = Some(self.settings.secret.clone());
+
+            if network_settings.identity.is_none() {
+                panic!("Zone servers have to share an identity, see NetworkSettings::identity");
+            }
+
+            resources
+                .get_mut::<ConnectionManager>()
+                .unwrap()
+                .set_peer_tys(network_settings.peer_tys.clone());
+
+            resources
+                .get_mut::<NetworkTypeRegistry>()
+                .unwrap()
+                .register::<ZoneServer>();
+        }
+
+        app_builder.add_resource(self.settings.clone());
+        app_builder.init_resource::<ZoneLinks>();
+
+        app_builder.add_system(zone_link_system);
+        app_builder.add_system(zone_restore_system);
+        app_builder.add_system_to_stage(stage::NETWORK_POST_RECEIVE, zone_receiving_system);
+        app_builder.add_system_to_stage(stage::NETWORK_PRE_SEND, zone_handoff_system);
+    }
+}
+
+fn start_link(
+    zone: &Zone,
+    zone_settings: &ZoneSettings,
+    connection_manager: &ConnectionManager,
+    network_settings: &NetworkSettings,
+) -> ZoneLink {
+    let mut link_settings = network_settings.clone();
+    link_settings.credentials = zone_settings.credentials.clone();
+    // every zone server shares one identity, so the secret only ever goes to one of them
+    link_settings.server_key = network_settings.identity.as_ref().map(Identity::public_key);
+
+    let receiver = start_attempt(
+        zone.addr.clone(),
+        connection_manager.detached(),
+        link_settings,
+        ActorTy::new::<ZoneServer>(),
+        Some(ActorTy::new::<ZoneServer>()),
+    );
+
+    ZoneLink::Connecting(Mutex::new(receiver))
+}
+
+pub fn zone_link_system(
+    zone_settings: Res<ZoneSettings>,
+    mut zone_links: ResMut<ZoneLinks>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    network_settings: Res<NetworkSettings>,
+    mut event_reader: Local<EventReader<ConnectionEvent>>,
+    connection_events: Res<Events<ConnectionEvent>>,
+) {
+    let now = Instant::now();
+
+    for event in event_reader.iter(&connection_events) {
+        if let ConnectionEvent::Disconnected { actor, cause, .. } = event {
+            zone_links.verified.remove(&actor.id());
+
+            for (zone_id, link) in zone_links.links.iter_mut() {
+                if let ZoneLink::Connected(actor_id) = link {
+                    if *actor_id == actor.id() {
+                        warn!("Lost the link to {:?}: {:?}", zone_id, cause);
+
+                        *link = ZoneLink::Down {
+                            retry_at: now + zone_settings.retry_delay,
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    for zone in &zone_settings.zones {
+        if zone.id == zone_settings.local {
+            continue;
+        }
+
+        let link = match zone_links.links.remove(&zone.id) {
+            None => start_link(zone, &zone_settings, &connection_manager, &network_settings),
+            Some(ZoneLink::Down { retry_at }) if now >= retry_at => {
+                start_link(zone, &zone_settings, &connection_manager, &network_settings)
+            }
+            Some(ZoneLink::Connecting(receiver)) => {
+                let done = receiver
+                    .lock()
+                    .unwrap()
+                    .try_iter()
+                    .filter_map(|progress| match progress {
+                        Progress::Done(result) => Some(result),
+                        Progress::Handshaking => None,
+                    })
+                    .next();
+
+                match done {
+                    None => ZoneLink::Connecting(receiver),
+                    Some(Ok((connected, event))) => {
+                        let event = connection_manager.attach(connected, event);
+
+                        match event {
+                            ConnectionEvent::Connected { actor, .. } => {
+                                info!("Linked to {:?} at {}", zone.id, zone.addr);
+
+                                let hello = Payload::Zone(ZonePayload::Hello {
+                                    zone: zone_settings.local,
+                                });
+
+                                // before any handoff, those skip the network handle too
+                                let result = match connection_manager.get_mut(actor.id()) {
+                                    Some(connection) => connection.send(vec![hello]),
+                                    None => Ok(()),
+                                };
+
+                                match result {
+                                    Ok(()) => ZoneLink::Connected(actor.id()),
+                                    Err(e) => {
+                                        warn!("Failed to greet {:?}: {:?}", zone.id, e);
+                                        connection_manager
+                                            .disconnect(actor.id(), DisconnectReason::Left);
+
+                                        ZoneLink::Down {
+                                            retry_at: now + zone_settings.retry_delay,
+                                        }
+                                    }
+                                }
+                            }
+                            _ => ZoneLink::Down {
+                                retry_at: now + zone_settings.retry_delay,
+                            },
+                        }
+                    }
+                    Some(Err(e)) => {
+                        warn!("Failed to link to {:?} at {}: {:?}", zone.id, zone.addr, e);
+
+                        ZoneLink::Down {
+                            retry_at: now + zone_settings.retry_delay,
+                        }
+                    }
+                }
+            }
+            Some(link) => link,
+        };
+
+        zone_links.links.insert(zone.id, link);
+    }
+}
+
+/// Greets links from other zone servers and spawns the entities they hand over.
+pub fn zone_receiving_system(
+    mut zone_links: ResMut<ZoneLinks>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut network_entity_registry: ResMut<NetworkEntityRegistry>,
+    mut network_handle: ResMut<NetworkHandle>,
+    mut event_reader: Local<EventReader<Message>>,
+    messages: Res<Events<Message>>,
+) {
+    for message in event_reader.iter(&messages) {
+        let zone_payload = match &message.payload {
+            Payload::Zone(zone_payload) => zone_payload,
+            _ => continue,
+        };
+
+        let sender = message.sender.id();
+
+        match zone_payload {
+            ZonePayload::Hello { zone } => {
+                if !message.sender.ty().is::<ZoneServer>() {
+                    warn!("{:?} claimed to be the server of {:?}", sender, zone);
+                    continue;
+                }
+
+                info!("{:?} linked as the server of {:?}", sender, zone);
+                zone_links.verified.insert(sender, *zone);
+            }
+            ZonePayload::Handoff(handoff) => {
+                if !zone_links.verified.contains_key(&sender) {
+                    warn!("Ignored a handoff from {:?}, it never said hello", sender);
+                    continue;
+                }
+
+                let mut spawnable: Box<dyn Spawnable> =
+                    match connection_manager.wire_format().decode(&handoff.spawnable) {
+                        Ok(spawnable) => spawnable,
+                        Err(e) => {
+                            warn!("Failed to decode a handoff from {:?}: {:?}", sender, e);
+                            continue;
+                        }
+                    };
+
+                if let Some((resume_token, player_id)) = handoff.owner {
+                    let actor_id = connection_manager.reserve_actor(resume_token, player_id);
+                    spawnable.set_owner(actor_id);
+                }
+
+                let network_entity = network_entity_registry.generate_network_entity();
+                network_handle.spawn_as(network_entity, NetworkTarget::All, spawnable);
+
+                zone_links
+                    .restores
+                    .push((network_entity, handoff.components.clone()));
+            }
+            ZonePayload::Left(_) | ZonePayload::Redirect { .. } => (),
+        }
+    }
+}
+
+/// Gives handed over entities their components back once they're spawned.
+pub fn zone_restore_system(world: &mut World, resources: &mut Resources) {
+    let network_entity_registry = resources.get::<NetworkEntityRegistry>().unwrap();
+    let network_type_registry = resources.get::<NetworkTypeRegistry>().unwrap();
+    let mut zone_links = resources.get_mut::<ZoneLinks>().unwrap();
+
+    let mut waiting = Vec::new();
+
+    for (network_entity, components) in std::mem::replace(&mut zone_links.restores, Vec::new()) {
+        let entity = match network_entity_registry.get(&network_entity) {
+            Some(entity) => *entity,
+            None => {
+                waiting.push((network_entity, components));
+                continue;
+            }
+        };
+
+        for (uuid, data) in &components {
+            match network_type_registry
+                .get(uuid)
+                .and_then(|network_type| network_type.component_restore_handler)
+            {
+                Some(restore) => restore(world, resources, entity, data),
+                None => warn!("Handed over an unknown component {}", uuid),
+            }
+        }
+    }
+
+    zone_links.restores = waiting;
+}
+
+/// Hands off every [`ZoneEntity`] that crossed into another zone, and redirects its owner.
+pub fn zone_handoff_system(world: &mut World, resources: &mut Resources) {
+    let zone_settings = resources.get::<ZoneSettings>().unwrap();
+    let zone_links = resources.get::<ZoneLinks>().unwrap();
+
+    let mut leaving = Vec::new();
+
+    for (entity, network_entity, transform, zone_entity) in
+        world.query::<(Entity, &NetworkEntity, &Transform, &ZoneEntity)>()
+    {
+        if let Some(zone) = zone_settings.handoff_zone(transform.translation.truncate()) {
+            if let Some(link) = zone_links.link(zone.id) {
+                leaving.push((
+                    entity,
+                    *network_entity,
+                    zone_entity.owner,
+                    zone.clone(),
+                    link,
+                ));
+            }
+        }
+    }
+
+    if leaving.is_empty() {
+        return;
+    }
+
+    // the snapshot handlers borrow resources of their own
+    let leaving: Vec<_> = {
+        let network_type_registry = resources.get::<NetworkTypeRegistry>().unwrap();
+
+        leaving
+            .into_iter()
+            .map(|(entity, network_entity, owner, zone, link)| {
+                let mut components = Vec::new();
+
+                for (uuid, network_type) in network_type_registry.iter() {
+                    if let Some(snapshot) = network_type.component_snapshot_handler {
+                        if let Some(data) = snapshot(world, resources, entity) {
+                            components.push((*uuid, data));
+                        }
+                    }
+                }
+
+                (entity, network_entity, owner, zone, link, components)
+            })
+            .collect()
+    };
+
+    let mut connection_manager = resources.get_mut::<ConnectionManager>().unwrap();
+    let mut spawn_manager = resources.get_mut::<SpawnManager>().unwrap();
+    let mut network_entity_registry = resources.get_mut::<NetworkEntityRegistry>().unwrap();
+    let mut network_handle = resources.get_mut::<NetworkHandle>().unwrap();
+    let mut connection_events = resources.get_mut::<Events<ConnectionEvent>>().unwrap();
+
+    for (entity, network_entity, owner, zone, link, components) in leaving {
+        let spawnable = match spawn_manager.unregister_spawn(network_entity) {
+            Some(Payload::Spawn { data, .. }) => data,
+            _ => continue,
+        };
+
+        let owner = owner.and_then(|owner| {
+            let player_id = connection_manager.get_actor(owner)?.player_id();
+
+            Some((owner, ResumeToken::generate(), player_id))
+        });
+
+        let handoff = Payload::Zone(ZonePayload::Handoff(Handoff {
+            spawnable,
+            components,
+            owner: owner.map(|(_, resume_token, player_id)| (resume_token, player_id)),
+        }));
+
+        // the new server has to know the owner before it shows up
+        if let Some(Err(e)) = connection_manager
+            .get_mut(link)
+            .map(|connection| connection.send(vec![handoff]))
+        {
+            warn!(
+                "Failed to hand off {:?} to {:?}: {:?}",
+                network_entity, zone.id, e
+            );
+            continue;
+        }
+
+        if let Some((owner, resume_token, _)) = owner {
+            let redirect = Payload::Zone(ZonePayload::Redirect {
+                addr: zone.addr.clone(),
+                resume_token,
+            });
+
+            if let Some(Err(e)) = connection_manager
+                .get_mut(owner)
+                .map(|connection| connection.send(vec![redirect]))
+            {
+                warn!("Failed to redirect {:?}: {:?}", owner, e);
+            }
+
+            if let Some(event) = connection_manager.disconnect(owner, DisconnectReason::Redirected)
+            {
+                connection_events.send(event);
+            }
+        }
+
+        info!("Handed off {:?} to {:?}", network_entity, zone.id);
+
+        network_handle.add_payload(
+            NetworkTarget::All,
+            Payload::Zone(ZonePayload::Left(network_entity)),
+        );
+
+        network_entity_registry.remove(&network_entity);
+        let _ = world.despawn(entity);
+    }
+}
+
+/// Follows [`ZonePayload::Redirect`] and drops entities that moved to another zone.
+pub fn zone_client_system(
+    commands: &mut Commands,
+    mut client_connection: ResMut<ClientConnection>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut network_entity_registry: ResMut<NetworkEntityRegistry>,
+    mut event_reader: Local<EventReader<Message>>,
+    messages: Res<Events<Message>>,
+) {
+    for message in event_reader.iter(&messages) {
+        // anyone else could move us to a server of their choosing
+        if Some(message.sender.id()) != client_connection.server() {
+            continue;
+        }
+
+        match &message.payload {
+            Payload::Zone(ZonePayload::Redirect { addr, resume_token }) => {
+                info!("Redirected to {}", addr);
+
+                // the new server spawns everything again
+                for entity in network_entity_registry.clear() {
+                    commands.despawn_recursive(entity);
+                }
+
+                connection_manager.set_resume_token(Some(*resume_token));
+                client_connection.redirect(addr.clone());
+            }
+            Payload::Zone(ZonePayload::Left(network_entity)) => {
+                if let Some(entity) = network_entity_registry.remove(network_entity) {
+                    commands.despawn_recursive(entity);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone_settings() -> ZoneSettings {
+        let zones = vec![
+            "1:0,0,1000,1000@127.0.0.1:9001".parse().unwrap(),
+            "2:1000,0,2000,1000@127.0.0.1:9002".parse().unwrap(),
+        ];
+
+        ZoneSettings::new(zones, ZoneId(1), "secret")
+    }
+
+    #[test]
+    fn zones_parse() {
+        let zone: Zone = " 3 : -500.5, -500 ,500,500@example.com:9001"
+            .parse()
+            .unwrap();
+
+        assert_eq!(zone.id, ZoneId(3));
+        assert_eq!(zone.min, Vec2::new(-500.5, -500.0));
+        assert_eq!(zone.max, Vec2::new(500.0, 500.0));
+        assert_eq!(zone.addr, "example.com:9001");
+    }
+
+    #[test]
+    fn malformed_zones_are_refused() {
+        for s in &[
+            "",
+            "1",
+            "1:0,0,1,1",
+            "1:0,0,1,1@",
+            "1:0,0,1@127.0.0.1:9001",
+            "1:0,0,1,1,1@127.0.0.1:9001",
+            "1:0,0,a,1@127.0.0.1:9001",
+            "a:0,0,1,1@127.0.0.1:9001",
+            "1@0,0,1,1:127.0.0.1",
+        ] {
+            assert!(s.parse::<Zone>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn zones_include_their_min_and_exclude_their_max() {
+        let zone_settings = zone_settings();
+
+        assert_eq!(
+            zone_settings.zone_at(Vec2::new(0.0, 0.0)).unwrap().id,
+            ZoneId(1)
+        );
+        assert_eq!(
+            zone_settings.zone_at(Vec2::new(999.9, 500.0)).unwrap().id,
+            ZoneId(1)
+        );
+        assert_eq!(
+            zone_settings.zone_at(Vec2::new(1000.0, 500.0)).unwrap().id,
+            ZoneId(2)
+        );
+        assert!(zone_settings.zone_at(Vec2::new(500.0, 1000.0)).is_none());
+        assert!(zone_settings.zone_at(Vec2::new(-0.1, 500.0)).is_none());
+    }
+
+    #[test]
+    fn handoffs_wait_until_past_the_margin() {
+        let zone_settings = zone_settings();
+
+        assert!(zone_settings
+            .handoff_zone(Vec2::new(500.0, 500.0))
+            .is_none());
+        assert!(zone_settings
+            .handoff_zone(Vec2::new(1000.0, 500.0))
+            .is_none());
+        assert!(zone_settings
+            .handoff_zone(Vec2::new(1031.9, 500.0))
+            .is_none());
+        assert_eq!(
+            zone_settings
+                .handoff_zone(Vec2::new(1032.0, 500.0))
+                .unwrap()
+                .id,
+            ZoneId(2)
+        );
+        // nowhere to go
+        assert!(zone_settings
+            .handoff_zone(Vec2::new(-100.0, 500.0))
+            .is_none());
+    }
+}
@@ -0,0 +1,94 @@
+use network::*;
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn spawn_burst_round_trips_compressed() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let settings = NetworkSettings::client();
+        let mut connection_manager = ConnectionManager::new(settings.actor_ty);
+
+        connection_manager
+            .add_connection(
+                TcpStream::connect(addr).unwrap(),
+                settings.connection_ty,
+                Handshake::new(HandshakeActorIds::None, &settings),
+                &settings,
+                None,
+            )
+            .unwrap();
+
+        connection_manager
+    });
+
+    let settings = NetworkSettings::server();
+    let mut server = ConnectionManager::new(settings.actor_ty);
+
+    let (stream, _) = listener.accept().unwrap();
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: server.generate_actor_id(),
+        sender_actor_id: server.get_local_actor().unwrap().id(),
+    };
+    server
+        .add_connection(
+            stream,
+            settings.connection_ty,
+            Handshake::new(actor_ids, &settings),
+            &settings,
+            None,
+        )
+        .unwrap();
+
+    let mut client = client.join().unwrap();
+
+    let payloads: Vec<_> = (0..5000)
+        .map(|i| Payload::Spawn {
+            network_entity: NetworkEntity(i),
+            data: format!("TileSpawnable {{ x: {}, y: {}, z: 0 }}", i % 64, i / 64).into_bytes(),
+        })
+        .collect();
+
+    let rooms = Rooms::new();
+    let targeted_payloads = payloads
+        .iter()
+        .map(|payload| {
+            (
+                NetworkTarget::ActorTy(ActorTy::new::<Client>()),
+                payload.clone(),
+            )
+        })
+        .collect();
+
+    assert!(server.send(targeted_payloads, &rooms).is_empty());
+
+    let mut received = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while received.len() < payloads.len() {
+        assert!(Instant::now() < deadline, "timed out waiting for payloads");
+
+        assert!(server.send(Vec::new(), &rooms).is_empty());
+
+        let (messages, events) = client.receive();
+        assert!(events.is_empty());
+        received.extend(messages.into_iter().map(|message| message.payload));
+
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert_eq!(received, payloads);
+
+    let (_, connection) = server
+        .connections()
+        .find(|(_, connection)| connection.actor().ty().is::<Client>())
+        .unwrap();
+
+    assert_eq!(connection.statistics().compressed_frames_sent, 1);
+    assert!(connection.statistics().compression_ratio() > 2.0);
+}
@@ -0,0 +1,67 @@
+use network::*;
+use std::marker::PhantomData;
+
+#[derive(NetworkTypeUuid)]
+#[uuid = "12345678-9abc-def0-1234-56789abcdef0"]
+struct Pinned;
+
+#[derive(NetworkTypeUuid)]
+struct Sword;
+
+#[derive(NetworkTypeUuid)]
+struct Shield;
+
+#[derive(NetworkTypeUuid)]
+struct Inventory<T>(PhantomData<T>);
+
+#[derive(NetworkTypeUuid)]
+#[uuid = "12345678-9abc-def0-1234-56789abcdef0"]
+struct PinnedInventory<T>(PhantomData<T>);
+
+#[derive(NetworkTypeUuid)]
+struct Borrowed<'a>(&'a str);
+
+mod elsewhere {
+    use network::*;
+
+    #[derive(NetworkTypeUuid)]
+    pub struct Sword;
+}
+
+#[test]
+fn explicit_uuids_are_kept() {
+    assert_eq!(Pinned::UUID.as_u128(), 0x123456789abcdef0123456789abcdef0);
+}
+
+#[test]
+fn hashed_uuids_follow_the_type_path() {
+    assert_eq!(
+        Sword::UUID.as_u128(),
+        type_path_hash(concat!(module_path!(), "::Sword"))
+    );
+    assert_ne!(Sword::UUID, Shield::UUID);
+    assert_ne!(Sword::UUID, elsewhere::Sword::UUID);
+}
+
+#[test]
+fn generic_instances_get_their_own_uuids() {
+    assert_ne!(Inventory::<Sword>::UUID, Inventory::<Shield>::UUID);
+    assert_ne!(Inventory::<Sword>::UUID, Sword::UUID);
+    assert_ne!(
+        Inventory::<Inventory<Sword>>::UUID,
+        Inventory::<Sword>::UUID
+    );
+    assert_ne!(
+        PinnedInventory::<Sword>::UUID,
+        PinnedInventory::<Shield>::UUID
+    );
+    assert_ne!(PinnedInventory::<Sword>::UUID, Pinned::UUID);
+}
+
+#[test]
+fn lifetimes_dont_change_uuids() {
+    assert_eq!(
+        Borrowed::UUID.as_u128(),
+        type_path_hash(concat!(module_path!(), "::Borrowed"))
+    );
+}
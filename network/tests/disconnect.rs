@@ -0,0 +1,107 @@
+use network::*;
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+/// A server and a client connected to it, with the client's actor id on the server.
+fn connect() -> (ConnectionManager, ConnectionManager, ActorId) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let settings = NetworkSettings::client();
+        let mut connection_manager = ConnectionManager::new(settings.actor_ty);
+
+        connection_manager
+            .add_connection(
+                TcpStream::connect(addr).unwrap(),
+                settings.connection_ty,
+                Handshake::new(HandshakeActorIds::None, &settings),
+                &settings,
+                None,
+            )
+            .unwrap();
+
+        connection_manager
+    });
+
+    let settings = NetworkSettings::server();
+    let mut server = ConnectionManager::new(settings.actor_ty);
+
+    let (stream, _) = listener.accept().unwrap();
+    let actor_id = server.generate_actor_id();
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: actor_id,
+        sender_actor_id: server.get_local_actor().unwrap().id(),
+    };
+    server
+        .add_connection(
+            stream,
+            settings.connection_ty,
+            Handshake::new(actor_ids, &settings),
+            &settings,
+            None,
+        )
+        .unwrap();
+
+    (server, client.join().unwrap(), actor_id)
+}
+
+fn remote_reason(connection_manager: &mut ConnectionManager) -> Option<DisconnectReason> {
+    thread::sleep(Duration::from_millis(50));
+
+    match connection_manager.receive().1.as_slice() {
+        [ConnectionEvent::Disconnected {
+            cause: DisconnectCause::Remote(reason),
+            ..
+        }] => Some(*reason),
+        _ => None,
+    }
+}
+
+#[test]
+fn kicked_clients_are_told_why() {
+    let (mut server, mut client, actor_id) = connect();
+
+    let event = server.disconnect(actor_id, DisconnectReason::Kicked);
+
+    assert!(matches!(
+        event,
+        Some(ConnectionEvent::Disconnected {
+            cause: DisconnectCause::Local(DisconnectReason::Kicked),
+            ..
+        })
+    ));
+    assert!(!server.sessions().is_held(actor_id));
+    assert_eq!(remote_reason(&mut client), Some(DisconnectReason::Kicked));
+}
+
+#[test]
+fn silent_clients_time_out() {
+    let (mut server, mut client, _) = connect();
+
+    server.set_timeout(Some(Duration::from_millis(30)));
+    thread::sleep(Duration::from_millis(50));
+
+    assert!(matches!(
+        server.receive().1.as_slice(),
+        [ConnectionEvent::Disconnected {
+            cause: DisconnectCause::Local(DisconnectReason::Timeout),
+            ..
+        }]
+    ));
+    assert_eq!(remote_reason(&mut client), Some(DisconnectReason::Timeout));
+}
+
+#[test]
+fn internal_actors_cant_be_disconnected() {
+    let (mut server, _client, _) = connect();
+    let local_actor_id = server.get_local_actor().unwrap().id();
+
+    assert!(server
+        .disconnect(local_actor_id, DisconnectReason::Kicked)
+        .is_none());
+    assert!(server.get_local_actor().is_some());
+}
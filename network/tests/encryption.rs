@@ -0,0 +1,306 @@
+use network::*;
+use std::{
+    io::prelude::*,
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+fn connect(
+    addr: SocketAddr,
+    listener: &TcpListener,
+    server_settings: &NetworkSettings,
+    client_settings: NetworkSettings,
+) -> (
+    Result<ConnectionManager, Error>,
+    Result<ConnectionManager, Error>,
+) {
+    let client = thread::spawn(move || {
+        let mut connection_manager = ConnectionManager::new(client_settings.actor_ty);
+
+        connection_manager
+            .add_connection(
+                TcpStream::connect(addr).unwrap(),
+                client_settings.connection_ty,
+                Handshake::new(HandshakeActorIds::None, &client_settings),
+                &client_settings,
+                None,
+            )
+            .map(|_| connection_manager)
+    });
+
+    let mut server = ConnectionManager::new(server_settings.actor_ty);
+    let (stream, _) = listener.accept().unwrap();
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: server.generate_actor_id(),
+        sender_actor_id: server.get_local_actor().unwrap().id(),
+    };
+
+    let server = server
+        .add_connection(
+            stream,
+            server_settings.connection_ty,
+            Handshake::new(actor_ids, server_settings),
+            server_settings,
+            None,
+        )
+        .map(|_| server);
+
+    (server, client.join().unwrap())
+}
+
+/// Forwards frames between two sockets, letting `tamper` change each frame along with its index
+/// in that direction.
+fn tampering_proxy(
+    listener: TcpListener,
+    server_addr: SocketAddr,
+    tamper: fn(usize, &mut Vec<u8>),
+) {
+    thread::spawn(move || {
+        let (client, _) = listener.accept().unwrap();
+        let server = TcpStream::connect(server_addr).unwrap();
+
+        let forward = |mut from: TcpStream, mut to: TcpStream| {
+            thread::spawn(move || {
+                let mut decoder = FrameDecoder::new();
+                let mut buf = [0u8; 4096];
+                let mut index = 0;
+
+                while let Ok(len) = from.read(&mut buf) {
+                    if len == 0 {
+                        return;
+                    }
+
+                    decoder.extend(&buf[..len]);
+
+                    while let Some(mut frame) = decoder.next_frame().unwrap() {
+                        tamper(index, &mut frame);
+                        index += 1;
+
+                        if to.write_all(&encode_frame(&frame)).is_err() {
+                            return;
+                        }
+                    }
+                }
+            })
+        };
+
+        forward(client.try_clone().unwrap(), server.try_clone().unwrap());
+        forward(server, client);
+    });
+}
+
+fn send_and_receive(
+    server: &mut ConnectionManager,
+    client: &mut ConnectionManager,
+    payload: Payload,
+) -> (Vec<Message>, Vec<ConnectionEvent>) {
+    let rooms = Rooms::new();
+    let target = NetworkTarget::ActorTy(ActorTy::new::<Client>());
+
+    assert!(server.send(vec![(target, payload)], &rooms).is_empty());
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    loop {
+        let (messages, events) = client.receive();
+
+        if !messages.is_empty() || !events.is_empty() || Instant::now() > deadline {
+            return (messages, events);
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn payload() -> Payload {
+    Payload::Spawn {
+        network_entity: NetworkEntity(7),
+        data: b"secret".to_vec(),
+    }
+}
+
+#[test]
+fn encrypted_payloads_round_trip() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut settings = NetworkSettings::server();
+    settings.encryption = Encryption::Required;
+    let mut client_settings = NetworkSettings::client();
+    client_settings.encryption = Encryption::Required;
+
+    let (server, client) = connect(addr, &listener, &settings, client_settings);
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+
+    let (messages, events) = send_and_receive(&mut server, &mut client, payload());
+
+    assert!(events.is_empty());
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].payload, payload());
+}
+
+#[test]
+fn tampered_frames_are_rejected() {
+    let server_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_addr = proxy_listener.local_addr().unwrap();
+
+    // the handshake, the key confirmation and the server's resume token go through untouched
+    tampering_proxy(
+        proxy_listener,
+        server_listener.local_addr().unwrap(),
+        |index, frame| {
+            if index >= 3 {
+                *frame.last_mut().unwrap() ^= 1;
+            }
+        },
+    );
+
+    let settings = NetworkSettings::server();
+    let (server, client) = connect(
+        proxy_addr,
+        &server_listener,
+        &settings,
+        NetworkSettings::client(),
+    );
+    let (mut server, mut client) = (server.unwrap(), client.unwrap());
+
+    let (messages, events) = send_and_receive(&mut server, &mut client, payload());
+
+    assert!(messages.is_empty());
+    assert_eq!(events.len(), 1);
+
+    match &events[0] {
+        ConnectionEvent::Disconnected {
+            cause: DisconnectCause::Error(Error::TamperedFrame),
+            ..
+        } => (),
+        event => panic!("expected a tampered frame, got {:?}", event),
+    }
+}
+
+#[test]
+fn downgraded_handshakes_are_rejected() {
+    let server_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_addr = proxy_listener.local_addr().unwrap();
+
+    tampering_proxy(
+        proxy_listener,
+        server_listener.local_addr().unwrap(),
+        |index, frame| {
+            if index == 0 {
+                let mut handshake: Handshake = serde_cbor::from_slice(frame).unwrap();
+                handshake.compression = false;
+                *frame = serde_cbor::to_vec(&handshake).unwrap();
+            }
+        },
+    );
+
+    let settings = NetworkSettings::server();
+    let (server, client) = connect(
+        proxy_addr,
+        &server_listener,
+        &settings,
+        NetworkSettings::client(),
+    );
+
+    assert!(matches!(server, Err(Error::TamperedFrame)));
+    assert!(matches!(client, Err(Error::TamperedFrame)));
+}
+
+#[test]
+fn plaintext_logins_are_refused_when_required() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut settings = NetworkSettings::server();
+    settings.encryption = Encryption::Disabled;
+    settings.require_login = true;
+    let mut client_settings = NetworkSettings::client();
+    client_settings.credentials = Some(Credentials::new("player", "password"));
+
+    let (server, client) = connect(addr, &listener, &settings, client_settings);
+
+    assert!(server.is_err());
+    assert!(matches!(client, Err(Error::EncryptionRequired)));
+}
+
+#[test]
+fn required_encryption_refuses_plaintext_peers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut settings = NetworkSettings::server();
+    settings.encryption = Encryption::Required;
+    let mut client_settings = NetworkSettings::client();
+    client_settings.encryption = Encryption::Disabled;
+
+    let (server, client) = connect(addr, &listener, &settings, client_settings);
+
+    assert!(matches!(server, Err(Error::EncryptionRequired)));
+    assert!(client.is_ok());
+}
+
+#[test]
+fn pinned_servers_connect() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let identity = Identity::generate();
+    let mut settings = NetworkSettings::server();
+    settings.identity = Some(identity.clone());
+    let mut client_settings = NetworkSettings::client();
+    client_settings.server_key = Some(identity.public_key());
+
+    let (server, client) = connect(addr, &listener, &settings, client_settings);
+
+    assert!(server.is_ok());
+    assert!(client.is_ok());
+}
+
+#[test]
+fn pinned_keys_refuse_other_servers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut settings = NetworkSettings::server();
+    settings.identity = Some(Identity::generate());
+    let mut client_settings = NetworkSettings::client();
+    client_settings.server_key = Some(Identity::generate().public_key());
+
+    let (_, client) = connect(addr, &listener, &settings, client_settings);
+
+    assert!(matches!(client, Err(Error::IdentityMismatch)));
+}
+
+#[test]
+fn pinned_keys_refuse_stripped_encryption() {
+    let server_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_addr = proxy_listener.local_addr().unwrap();
+
+    tampering_proxy(
+        proxy_listener,
+        server_listener.local_addr().unwrap(),
+        |index, frame| {
+            if index == 0 {
+                let mut handshake: Handshake = serde_cbor::from_slice(frame).unwrap();
+                handshake.public_key = None;
+                *frame = serde_cbor::to_vec(&handshake).unwrap();
+            }
+        },
+    );
+
+    let identity = Identity::generate();
+    let mut settings = NetworkSettings::server();
+    settings.encryption = Encryption::Enabled;
+    settings.identity = Some(identity.clone());
+    let mut client_settings = NetworkSettings::client();
+    client_settings.server_key = Some(identity.public_key());
+
+    let (_, client) = connect(proxy_addr, &server_listener, &settings, client_settings);
+
+    assert!(matches!(client, Err(Error::IdentityMismatch)));
+}
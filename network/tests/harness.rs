@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use network::*;
+use std::{
+    net::TcpStream,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, NetworkTypeUuid, SyncableComponent)]
+struct Score(u32);
+
+#[derive(Serialize, Deserialize)]
+struct ScoreSpawnable {
+    score: u32,
+}
+
+#[typetag::serde]
+impl Spawnable for ScoreSpawnable {
+    fn spawn(
+        &self,
+        commands: &mut Commands,
+        _resources: &Resources,
+        _ctx: &SpawnContext,
+        bundle: SpawnBundle,
+    ) -> Entity {
+        commands
+            .spawn(bundle)
+            .with(Score(self.score))
+            .with(ComponentSync::<Score>::ty(ActorTy::new::<Server>()))
+            .current_entity()
+            .unwrap()
+    }
+}
+
+#[test]
+fn server_spawns_and_syncs_to_every_client() {
+    let mut network = TestNetwork::new(3, |app_builder| {
+        app_builder.add_component_sync::<Score>();
+    });
+
+    network
+        .server_mut()
+        .resources
+        .get_mut::<NetworkHandle>()
+        .unwrap()
+        .spawn(NetworkTarget::All, ScoreSpawnable { score: 1 });
+
+    let spawned = network.run_until(20, |network| {
+        network
+            .clients()
+            .iter()
+            .all(|client| client.network_entity(NetworkEntity(0)).is_some())
+    });
+    assert!(spawned);
+
+    network
+        .client(2)
+        .assert_network_component(NetworkEntity(0), &Score(1));
+
+    let entity = network.server().network_entity(NetworkEntity(0)).unwrap();
+    network
+        .server_mut()
+        .world
+        .get_mut::<Score>(entity)
+        .unwrap()
+        .0 = 5;
+
+    let synced = network.run_until(20, |network| {
+        network
+            .clients()
+            .iter()
+            .all(|client| client.network_component(NetworkEntity(0)) == Some(Score(5)))
+    });
+    assert!(synced);
+}
+
+#[test]
+fn stalled_handshakes_dont_hold_up_the_server() {
+    let mut network = TestNetwork::new(0, |_| ());
+    let addr = network
+        .server()
+        .resources
+        .get::<Listener>()
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    // connects and never says a word
+    let _stalled = TcpStream::connect(addr).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let settings = NetworkSettings::client();
+        let mut connection_manager = ConnectionManager::new(settings.actor_ty);
+
+        let result = connection_manager.add_connection(
+            TcpStream::connect(addr).unwrap(),
+            settings.connection_ty,
+            Handshake::new(HandshakeActorIds::None, &settings),
+            &settings,
+            None,
+        );
+
+        sender.send(result.is_ok()).unwrap();
+    });
+
+    let start = Instant::now();
+
+    let connected = loop {
+        network.server_mut().update();
+
+        if let Ok(connected) = receiver.try_recv() {
+            break connected;
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    };
+
+    assert!(connected);
+    assert!(start.elapsed() < HANDSHAKE_TIMEOUT);
+}
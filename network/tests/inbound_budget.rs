@@ -0,0 +1,110 @@
+use network::*;
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+/// A server with `budget` and a client connected to it.
+fn connect(budget: InboundBudget) -> (ConnectionManager, ConnectionManager) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let settings = NetworkSettings::client();
+        let mut connection_manager = ConnectionManager::new(settings.actor_ty);
+
+        connection_manager
+            .add_connection(
+                TcpStream::connect(addr).unwrap(),
+                settings.connection_ty,
+                Handshake::new(HandshakeActorIds::None, &settings),
+                &settings,
+                None,
+            )
+            .unwrap();
+
+        connection_manager
+    });
+
+    let mut settings = NetworkSettings::server();
+    settings.inbound_budget = Some(budget);
+
+    let mut server = ConnectionManager::new(settings.actor_ty);
+
+    let (stream, _) = listener.accept().unwrap();
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: server.generate_actor_id(),
+        sender_actor_id: server.get_local_actor().unwrap().id(),
+    };
+    server
+        .add_connection(
+            stream,
+            settings.connection_ty,
+            Handshake::new(actor_ids, &settings),
+            &settings,
+            None,
+        )
+        .unwrap();
+
+    (server, client.join().unwrap())
+}
+
+/// Sends 10 pings from `client`, one frame each.
+fn flood(client: &mut ConnectionManager) {
+    let rooms = Rooms::new();
+
+    for id in 0..10 {
+        let ping = (
+            NetworkTarget::ActorTy(ActorTy::new::<Server>()),
+            Payload::Ping { id },
+        );
+        assert!(client.send(vec![ping], &rooms).is_empty());
+    }
+
+    thread::sleep(Duration::from_millis(100));
+}
+
+#[test]
+fn over_budget_payloads_are_dropped() {
+    let (mut server, mut client) = connect(InboundBudget {
+        payloads_per_second: 3,
+        bytes_per_second: 64 * 1024,
+        action: BudgetAction::Drop,
+    });
+
+    flood(&mut client);
+
+    let (_, events) = server.receive();
+    assert!(events.is_empty(), "{:?}", events);
+
+    let (_, connection) = server
+        .connections()
+        .find(|(_, connection)| connection.actor().ty().is::<Client>())
+        .unwrap();
+
+    let statistics = connection.statistics();
+    assert_eq!(statistics.payload_kinds[&PayloadKind::Ping].received, 3);
+    assert_eq!(statistics.payloads_dropped, 7);
+}
+
+#[test]
+fn over_budget_connections_are_disconnected() {
+    let (mut server, mut client) = connect(InboundBudget {
+        payloads_per_second: 3,
+        bytes_per_second: 64 * 1024,
+        action: BudgetAction::Disconnect,
+    });
+
+    flood(&mut client);
+
+    let (_, events) = server.receive();
+
+    assert!(matches!(
+        events.as_slice(),
+        [ConnectionEvent::Disconnected {
+            cause: DisconnectCause::Error(Error::BudgetExceeded),
+            ..
+        }]
+    ));
+}
@@ -0,0 +1,91 @@
+use network::*;
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+struct Chat;
+network_uuid!(Chat = 99887766);
+
+/// Accepts a connection from `client` as `server`, the client sees the server as `server_ty`.
+fn connect(
+    server: &mut ConnectionManager,
+    client: ConnectionManager,
+    server_ty: ActorTy,
+) -> ConnectionManager {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let mut client = client;
+        let settings = NetworkSettings::client();
+
+        client
+            .add_connection(
+                TcpStream::connect(addr).unwrap(),
+                server_ty,
+                Handshake::new(HandshakeActorIds::None, &settings),
+                &settings,
+                None,
+            )
+            .unwrap();
+
+        client
+    });
+
+    let settings = NetworkSettings::server();
+    let (stream, _) = listener.accept().unwrap();
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: server.generate_actor_id(),
+        sender_actor_id: server.get_local_actor().unwrap().id(),
+    };
+    server
+        .add_connection(
+            stream,
+            settings.connection_ty,
+            Handshake::new(actor_ids, &settings),
+            &settings,
+            None,
+        )
+        .unwrap();
+
+    client.join().unwrap()
+}
+
+#[test]
+fn payloads_go_to_the_targeted_server() {
+    let settings = NetworkSettings::server();
+    let mut game = ConnectionManager::new(settings.actor_ty);
+    let mut chat = ConnectionManager::new(ActorTy::new::<Chat>());
+
+    let client = ConnectionManager::new(NetworkSettings::client().actor_ty);
+    let client = connect(&mut game, client, settings.connection_ty);
+    let local_actor_id = client.get_local_actor().unwrap().id();
+    let mut client = connect(&mut chat, client, ActorTy::new::<Chat>());
+
+    // only the main connection assigns our id
+    assert_eq!(client.get_local_actor().unwrap().id(), local_actor_id);
+    assert_eq!(client.connections().count(), 3);
+
+    let rooms = Rooms::new();
+    let chat_target = NetworkTarget::ActorTy(ActorTy::new::<Chat>());
+
+    assert_eq!(client.get_targeted_actor_ids(&chat_target, &rooms).len(), 1);
+
+    client.send(vec![(chat_target, Payload::Ping { id: 1 })], &rooms);
+    thread::sleep(Duration::from_millis(50));
+
+    assert!(chat.receive().1.is_empty());
+    assert!(game.receive().1.is_empty());
+
+    let pinged = |connection_manager: &ConnectionManager| {
+        connection_manager
+            .connections()
+            .filter(|(_, connection)| connection.statistics().payloads_received > 0)
+            .count()
+    };
+
+    assert_eq!(pinged(&chat), 1);
+    assert_eq!(pinged(&game), 0);
+}
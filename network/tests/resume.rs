@@ -0,0 +1,135 @@
+use network::*;
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+/// Connects `client` to `server`, returning the events of both ends.
+fn connect(
+    server: &mut ConnectionManager,
+    server_settings: &NetworkSettings,
+    mut client: ConnectionManager,
+    client_settings: NetworkSettings,
+) -> (ConnectionEvent, ConnectionManager, ConnectionEvent) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let event = client
+            .add_connection(
+                TcpStream::connect(addr).unwrap(),
+                client_settings.connection_ty,
+                Handshake::new(HandshakeActorIds::None, &client_settings),
+                &client_settings,
+                None,
+            )
+            .unwrap();
+
+        (client, event)
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: server.generate_actor_id(),
+        sender_actor_id: server.get_local_actor().unwrap().id(),
+    };
+    let event = server
+        .add_connection(
+            stream,
+            server_settings.connection_ty,
+            Handshake::new(actor_ids, server_settings),
+            server_settings,
+            None,
+        )
+        .unwrap();
+
+    let (client, client_event) = client.join().unwrap();
+
+    (event, client, client_event)
+}
+
+fn resumed(event: &ConnectionEvent) -> Option<ActorId> {
+    match event {
+        ConnectionEvent::Connected {
+            actor,
+            resumed: true,
+            ..
+        } => Some(actor.id()),
+        _ => None,
+    }
+}
+
+fn client_with_token(resume_token: Option<ResumeToken>) -> ConnectionManager {
+    let mut client = ConnectionManager::new(NetworkSettings::client().actor_ty);
+    client.set_resume_token(resume_token);
+    client
+}
+
+#[test]
+fn disconnected_actors_are_resumed() {
+    let settings = NetworkSettings::server();
+    let mut server = ConnectionManager::new(settings.actor_ty);
+
+    let (_, client, _) = connect(
+        &mut server,
+        &settings,
+        client_with_token(None),
+        NetworkSettings::client(),
+    );
+    let actor_id = client.get_local_actor().unwrap().id();
+
+    server.remove(actor_id);
+
+    let (event, client, _) = connect(
+        &mut server,
+        &settings,
+        client_with_token(client.resume_token()),
+        NetworkSettings::client(),
+    );
+
+    assert_eq!(resumed(&event), Some(actor_id));
+    assert_eq!(client.get_local_actor().unwrap().id(), actor_id);
+}
+
+#[test]
+fn connected_actors_cant_be_taken_over() {
+    let settings = NetworkSettings::server();
+    let mut server = ConnectionManager::new(settings.actor_ty);
+
+    let (_, client, _) = connect(
+        &mut server,
+        &settings,
+        client_with_token(None),
+        NetworkSettings::client(),
+    );
+    let actor_id = client.get_local_actor().unwrap().id();
+
+    let (event, _, _) = connect(
+        &mut server,
+        &settings,
+        client_with_token(client.resume_token()),
+        NetworkSettings::client(),
+    );
+
+    assert_eq!(resumed(&event), None);
+    assert!(server.get(actor_id).is_some());
+}
+
+#[test]
+fn tokens_are_only_issued_over_encrypted_connections() {
+    let mut settings = NetworkSettings::server();
+    settings.encryption = Encryption::Disabled;
+    let mut server = ConnectionManager::new(settings.actor_ty);
+
+    let mut client_settings = NetworkSettings::client();
+    client_settings.encryption = Encryption::Disabled;
+
+    let (_, client, _) = connect(
+        &mut server,
+        &settings,
+        client_with_token(None),
+        client_settings,
+    );
+
+    assert_eq!(client.resume_token(), None);
+}
@@ -0,0 +1,358 @@
+use bevy::{
+    prelude::*,
+    reflect::{TypeRegistry, Uuid},
+};
+use network::*;
+use proptest::{collection::vec, option, prelude::*};
+use std::time::Duration;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, NetworkTypeUuid, SyncableComponent)]
+struct Health {
+    current: u32,
+    regen: f32,
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct Stamina(u16);
+
+serde_sync!(Stamina = 281046918346178231958130);
+
+fn uuid() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+fn data() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..64)
+}
+
+fn network_entity() -> impl Strategy<Value = NetworkEntity> {
+    any::<u64>().prop_map(NetworkEntity)
+}
+
+fn resume_token() -> impl Strategy<Value = ResumeToken> {
+    any::<[u8; 16]>().prop_map(ResumeToken)
+}
+
+fn wire_format() -> impl Strategy<Value = WireFormat> {
+    prop_oneof![Just(WireFormat::Cbor), Just(WireFormat::Bincode)]
+}
+
+fn disconnect_reason() -> impl Strategy<Value = DisconnectReason> {
+    prop_oneof![
+        Just(DisconnectReason::Kicked),
+        Just(DisconnectReason::ServerShutdown),
+        Just(DisconnectReason::Left),
+        Just(DisconnectReason::VersionMismatch),
+        Just(DisconnectReason::Timeout),
+        Just(DisconnectReason::Redirected),
+        prop_oneof![
+            Just(Rejection::Banned),
+            Just(Rejection::NotAllowed),
+            Just(Rejection::RateLimited),
+            Just(Rejection::ServerFull),
+        ]
+        .prop_map(DisconnectReason::Rejected),
+    ]
+}
+
+fn zone_payload() -> impl Strategy<Value = ZonePayload> {
+    let handoff = (
+        data(),
+        vec((uuid(), data()), 0..4),
+        option::of((resume_token(), option::of(any::<u64>().prop_map(PlayerId)))),
+    )
+        .prop_map(|(spawnable, components, owner)| Handoff {
+            spawnable,
+            components,
+            owner,
+        });
+
+    prop_oneof![
+        any::<u32>().prop_map(|zone| ZonePayload::Hello { zone: ZoneId(zone) }),
+        handoff.prop_map(ZonePayload::Handoff),
+        network_entity().prop_map(ZonePayload::Left),
+        (any::<String>(), resume_token())
+            .prop_map(|(addr, resume_token)| ZonePayload::Redirect { addr, resume_token }),
+    ]
+}
+
+fn payload() -> impl Strategy<Value = Payload> {
+    prop_oneof![
+        (network_entity(), uuid(), data()).prop_map(|(target_entity, network_type_uuid, data)| {
+            Payload::ComponentUpdate {
+                target_entity,
+                network_type_uuid,
+                data,
+            }
+        }),
+        (network_entity(), data()).prop_map(|(network_entity, data)| Payload::Spawn {
+            network_entity,
+            data
+        }),
+        any::<u64>().prop_map(|id| Payload::Ping { id }),
+        any::<u64>().prop_map(|id| Payload::Pong { id }),
+        prop_oneof![
+            any::<String>().prop_map(AdminPayload::Command),
+            any::<String>().prop_map(AdminPayload::Response),
+            any::<String>().prop_map(AdminPayload::Broadcast),
+        ]
+        .prop_map(Payload::Admin),
+        disconnect_reason().prop_map(Payload::Goodbye),
+        zone_payload().prop_map(Payload::Zone),
+    ]
+}
+
+fn handshake() -> impl Strategy<Value = Handshake> {
+    let actor_ids = prop_oneof![
+        Just(HandshakeActorIds::None),
+        (any::<u64>(), any::<u64>()).prop_map(|(sender, receiver)| {
+            HandshakeActorIds::Override {
+                sender_actor_id: ActorId(sender),
+                receiver_actor_id: ActorId(receiver),
+            }
+        }),
+    ];
+
+    (
+        actor_ids,
+        any::<(bool, bool, bool)>(),
+        option::of(any::<[u8; 32]>()),
+        any::<bool>(),
+        wire_format(),
+        option::of(disconnect_reason()),
+        any::<u32>(),
+        option::of(uuid().prop_map(ActorTy)),
+        option::of(any::<u64>().prop_map(Duration::from_micros)),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(
+                actor_ids,
+                (compression, login, login_required),
+                public_key,
+                resume_token,
+                wire_format,
+                goodbye,
+                version,
+                actor_ty,
+                tick_duration,
+                identified,
+            )| Handshake {
+                actor_ids,
+                compression,
+                public_key,
+                login,
+                login_required,
+                resume_token,
+                wire_format,
+                goodbye,
+                version,
+                actor_ty,
+                tick_duration,
+                identified,
+            },
+        )
+}
+
+/// Feeds `stream` to a decoder in chunks of the given lengths, taking frames as they complete.
+fn decode_chunked(stream: &[u8], chunk_lens: &[usize]) -> Result<Vec<Vec<u8>>, Error> {
+    let mut decoder = FrameDecoder::new();
+    let mut frames = Vec::new();
+    let mut rest = stream;
+    let mut chunk_lens = chunk_lens.iter().cycle();
+
+    while !rest.is_empty() {
+        let len = (*chunk_lens.next().unwrap()).min(rest.len());
+        decoder.extend(&rest[..len]);
+        rest = &rest[len..];
+
+        while let Some(frame) = decoder.next_frame()? {
+            frames.push(frame);
+        }
+    }
+
+    Ok(frames)
+}
+
+/// Like the game's, so decoding gets past the type lookup.
+fn type_registry() -> TypeRegistry {
+    let type_registry = TypeRegistry::default();
+    type_registry.write().register::<Vec3>();
+    type_registry.write().register::<Quat>();
+    type_registry.write().register::<Transform>();
+    type_registry.write().register::<GlobalTransform>();
+    type_registry
+}
+
+fn angle(rotation: Quat) -> f32 {
+    2.0 * rotation.z.atan2(rotation.w)
+}
+
+proptest! {
+    #[test]
+    fn payloads_round_trip(
+        payloads in vec(payload(), 0..8),
+        wire_format in wire_format(),
+        threshold in 0usize..256,
+    ) {
+        let bytes = wire_format.encode(&payloads).unwrap();
+        let compression = CompressionSettings { threshold, level: 6 };
+        let frame = compress_frame(&bytes, Some(&compression)).unwrap();
+
+        let decoded: Vec<Payload> = wire_format.decode(&decompress_frame(&frame).unwrap()).unwrap();
+
+        prop_assert_eq!(decoded, payloads);
+    }
+
+    #[test]
+    fn arbitrary_frames_never_panic(bytes in vec(any::<u8>(), 0..512)) {
+        for wire_format in [WireFormat::Cbor, WireFormat::Bincode].iter() {
+            let _ = wire_format.decode::<Vec<Payload>>(&bytes);
+            let _ = decompress_frame(&bytes)
+                .and_then(|bytes| wire_format.decode::<Vec<Payload>>(&bytes));
+        }
+    }
+
+    #[test]
+    fn handshakes_round_trip(handshake in handshake()) {
+        let bytes = serde_cbor::to_vec(&handshake).unwrap();
+        let decoded: Handshake = serde_cbor::from_slice(&bytes).unwrap();
+
+        prop_assert_eq!(serde_cbor::to_vec(&decoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn arbitrary_handshakes_never_panic(bytes in vec(any::<u8>(), 0..512)) {
+        let _ = serde_cbor::from_slice::<Handshake>(&bytes);
+    }
+
+    #[test]
+    fn frames_survive_any_split(
+        frames in vec(vec(any::<u8>(), 0..256), 0..8),
+        chunk_lens in vec(1usize..64, 1..16),
+    ) {
+        let stream: Vec<u8> = frames.iter().flat_map(|frame| encode_frame(frame)).collect();
+
+        prop_assert_eq!(decode_chunked(&stream, &chunk_lens).unwrap(), frames);
+    }
+
+    #[test]
+    fn arbitrary_streams_never_panic(
+        stream in vec(any::<u8>(), 0..512),
+        chunk_lens in vec(1usize..64, 1..16),
+    ) {
+        if let Ok(frames) = decode_chunked(&stream, &chunk_lens) {
+            prop_assert!(frames.iter().all(|frame| frame.len() <= MAX_FRAME_LEN));
+        }
+    }
+
+    #[test]
+    fn derived_components_round_trip(
+        current in any::<u32>(),
+        regen in -1e6f32..1e6,
+        name in any::<String>(),
+        wire_format in wire_format(),
+    ) {
+        let type_registry = TypeRegistry::default();
+        let health = Health { current, regen, name };
+
+        let bytes = health.to_bytes(&type_registry, wire_format);
+
+        prop_assert_eq!(Health::from_bytes(&bytes, &type_registry, wire_format).unwrap(), health);
+    }
+
+    #[test]
+    fn serde_synced_components_round_trip(stamina in any::<u16>(), wire_format in wire_format()) {
+        let type_registry = TypeRegistry::default();
+        let bytes = Stamina(stamina).to_bytes(&type_registry, wire_format);
+
+        prop_assert_eq!(
+            Stamina::from_bytes(&bytes, &type_registry, wire_format).unwrap(),
+            Stamina(stamina)
+        );
+    }
+
+    #[test]
+    fn compact_transforms_round_trip(
+        translation in (-16000f32..16000.0, -16000f32..16000.0, -512f32..512.0)
+            .prop_map(|(x, y, z)| Vec3::new(x, y, z)),
+        rotation in -std::f32::consts::PI..std::f32::consts::PI,
+        scale in (-1e6f32..1e6, -1e6f32..1e6, -1e6f32..1e6).prop_map(|(x, y, z)| Vec3::new(x, y, z)),
+        scale_sent in any::<bool>(),
+    ) {
+        let settings = CompactTransformSettings::default();
+        let transform = Transform {
+            translation,
+            rotation: Quat::from_rotation_z(rotation),
+            scale,
+        };
+
+        let last_scale = if scale_sent { Some(scale) } else { None };
+        let bytes = settings.encode(&transform, last_scale);
+        let current = Transform {
+            scale,
+            ..Default::default()
+        };
+        let decoded = settings.decode(&bytes, &current).unwrap();
+
+        prop_assert!(decoded.translation.abs_diff_eq(transform.translation, settings.x.step));
+
+        let error = (angle(decoded.rotation) - rotation).abs();
+        let error = error.min(std::f32::consts::PI * 2.0 - error);
+        prop_assert!(error <= settings.rotation.step);
+
+        prop_assert_eq!(decoded.scale, transform.scale);
+    }
+
+    #[test]
+    fn unchanged_scales_are_left_out(scale in (-1e6f32..1e6, -1e6f32..1e6, -1e6f32..1e6)) {
+        let settings = CompactTransformSettings::default();
+        let transform = Transform {
+            scale: Vec3::new(scale.0, scale.1, scale.2),
+            ..Default::default()
+        };
+
+        let full = settings.encode(&transform, None);
+        let diffed = settings.encode(&transform, Some(transform.scale));
+
+        prop_assert!(diffed.len() < full.len());
+        prop_assert_eq!(settings.decode(&diffed, &transform).unwrap().scale, transform.scale);
+    }
+
+    #[test]
+    fn arbitrary_components_never_panic(
+        bytes in vec(any::<u8>(), 0..128),
+        wire_format in wire_format(),
+    ) {
+        let type_registry = type_registry();
+
+        let _ = Health::from_bytes(&bytes, &type_registry, wire_format);
+        let _ = Stamina::from_bytes(&bytes, &type_registry, wire_format);
+        let _ = Transform::from_bytes(&bytes, &type_registry, wire_format);
+        let _ = CompactTransformSettings::default().decode(&bytes, &Transform::default());
+    }
+
+    #[test]
+    fn bit_readers_stop_at_the_end(bytes in vec(any::<u8>(), 0..16), bits in 1u32..64) {
+        let mut reader = BitReader::new(&bytes);
+        let mut read = 0;
+
+        while reader.read_bits(bits).is_ok() {
+            read += bits as usize;
+        }
+
+        prop_assert!(read <= bytes.len() * 8);
+    }
+}
+
+#[test]
+fn reflected_components_of_another_type_are_refused() {
+    let type_registry = type_registry();
+    let bytes = GlobalTransform::default().to_bytes(&type_registry, WireFormat::Cbor);
+
+    assert!(matches!(
+        Transform::from_bytes(&bytes, &type_registry, WireFormat::Cbor),
+        Err(Error::InvalidComponent)
+    ));
+}
@@ -0,0 +1,157 @@
+use network::*;
+use std::{
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Connects `client` to `server` as `connection_ty`, claiming `claim`, returning the events of
+/// both ends.
+fn connect(
+    server: &mut ConnectionManager,
+    server_settings: &NetworkSettings,
+    mut client: ConnectionManager,
+    client_settings: NetworkSettings,
+    claim: Option<ActorTy>,
+) -> (ConnectionEvent, ConnectionManager) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connection_ty = claim.unwrap_or(client_settings.connection_ty);
+
+    let client = thread::spawn(move || {
+        let mut handshake = Handshake::new(HandshakeActorIds::None, &client_settings);
+        handshake.actor_ty = claim;
+
+        client
+            .add_connection(
+                TcpStream::connect(addr).unwrap(),
+                connection_ty,
+                handshake,
+                &client_settings,
+                None,
+            )
+            .unwrap();
+
+        client
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let actor_ids = HandshakeActorIds::Override {
+        receiver_actor_id: server.generate_actor_id(),
+        sender_actor_id: server.get_local_actor().unwrap().id(),
+    };
+    let event = server
+        .add_connection(
+            stream,
+            server_settings.connection_ty,
+            Handshake::new(actor_ids, server_settings),
+            server_settings,
+            None,
+        )
+        .unwrap();
+
+    (event, client.join().unwrap())
+}
+
+fn receive(connection_manager: &mut ConnectionManager) -> Vec<Message> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    loop {
+        let (messages, _) = connection_manager.receive();
+
+        if !messages.is_empty() || Instant::now() > deadline {
+            return messages;
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn connected(event: &ConnectionEvent) -> (ActorId, bool) {
+    match event {
+        ConnectionEvent::Connected { actor, resumed, .. } => (actor.id(), *resumed),
+        event => panic!("expected a connection, got {:?}", event),
+    }
+}
+
+#[test]
+fn handed_off_owners_resume_on_the_new_server() {
+    let identity = Identity::generate();
+    let mut server_settings = NetworkSettings::server();
+    server_settings.identity = Some(identity.clone());
+    server_settings.peer_tys = vec![ActorTy::new::<ZoneServer>()];
+    server_settings.peer_secret = Some("zones".to_string());
+
+    let mut link_settings = server_settings.clone();
+    link_settings.server_key = Some(identity.public_key());
+    let mut client_settings = NetworkSettings::client();
+    client_settings.server_key = Some(identity.public_key());
+
+    let mut first = ConnectionManager::new(server_settings.actor_ty);
+    let mut second = ConnectionManager::new(server_settings.actor_ty);
+
+    // the first zone server links to the second, keeping its own actor
+    let mut linking = first.detached();
+    linking.set_keep_local_actor_id(true);
+    let (link_event, linked) = connect(
+        &mut second,
+        &server_settings,
+        linking,
+        link_settings,
+        Some(ActorTy::new::<ZoneServer>()),
+    );
+    let link = first.attach(linked, link_event);
+    let (link, _) = connected(&link);
+
+    let (owner_event, mut client) = connect(
+        &mut first,
+        &server_settings,
+        ConnectionManager::new(client_settings.actor_ty),
+        client_settings.clone(),
+        None,
+    );
+    let (owner, _) = connected(&owner_event);
+
+    let resume_token = ResumeToken::generate();
+    let handoff = Payload::Zone(ZonePayload::Handoff(Handoff {
+        spawnable: Vec::new(),
+        components: Vec::new(),
+        owner: Some((resume_token, None)),
+    }));
+    first.get_mut(link).unwrap().send(vec![handoff]).unwrap();
+
+    let messages = receive(&mut second);
+    assert!(messages[0].sender.ty().is::<ZoneServer>());
+
+    let reserved = match &messages[0].payload {
+        Payload::Zone(ZonePayload::Handoff(Handoff {
+            owner: Some((resume_token, player_id)),
+            ..
+        })) => second.reserve_actor(*resume_token, *player_id),
+        payload => panic!("expected a handoff, got {:?}", payload),
+    };
+
+    let redirect = Payload::Zone(ZonePayload::Redirect {
+        addr: "second".to_string(),
+        resume_token,
+    });
+    first.get_mut(owner).unwrap().send(vec![redirect]).unwrap();
+
+    let redirected = match &receive(&mut client)[0].payload {
+        Payload::Zone(ZonePayload::Redirect { resume_token, .. }) => *resume_token,
+        payload => panic!("expected a redirect, got {:?}", payload),
+    };
+
+    let mut follower = ConnectionManager::new(client_settings.actor_ty);
+    follower.set_resume_token(Some(redirected));
+    let (event, follower) = connect(
+        &mut second,
+        &server_settings,
+        follower,
+        client_settings,
+        None,
+    );
+
+    assert_eq!(connected(&event), (reserved, true));
+    assert_eq!(follower.get_local_actor().unwrap().id(), reserved);
+}
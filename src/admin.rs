@@ -0,0 +1,99 @@
+use crate::*;
+use std::collections::BTreeMap;
+
+/// Spawns whatever the arguments of `spawn <name>` describe and says what it spawned, `None` if
+/// the arguments don't fit.
+pub type AdminSpawnHandler = fn(&[String], &mut NetworkHandle) -> Option<String>;
+
+/// What `spawn <name> <args>` can spawn, tiles unless more are registered.
+pub struct AdminSpawns {
+    /// Handlers and their arguments, by name.
+    spawns: BTreeMap<String, (AdminSpawnHandler, &'static str)>,
+}
+
+impl Default for AdminSpawns {
+    fn default() -> Self {
+        let mut admin_spawns = Self {
+            spawns: BTreeMap::new(),
+        };
+
+        admin_spawns.register("tile", "<x> <y> <z>", spawn_tile);
+        admin_spawns
+    }
+}
+
+impl AdminSpawns {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        usage: &'static str,
+        handler: AdminSpawnHandler,
+    ) -> &mut Self {
+        self.spawns.insert(name.into(), (handler, usage));
+        self
+    }
+
+    pub fn spawn(&self, name: &str, args: &[String], network_handle: &mut NetworkHandle) -> String {
+        match self.spawns.get(name) {
+            Some((handler, usage)) => handler(args, network_handle)
+                .unwrap_or_else(|| format!("usage: spawn {} {}", name, usage)),
+            None => format!(
+                "unknown spawnable {}, expected one of {}",
+                name,
+                self.spawns.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+/// Handles the admin commands that need the game, teleport and spawn.
+pub fn admin_command_system(
+    mut event_reader: Local<EventReader<AdminCommand>>,
+    admin_commands: Res<Events<AdminCommand>>,
+    mut admin_responses: ResMut<Events<AdminResponse>>,
+    admin_spawns: Res<AdminSpawns>,
+    mut network_handle: ResMut<NetworkHandle>,
+    mut query: Query<(&PlayerOwner, &mut TargetPosition, &mut Transform)>,
+) {
+    for command in event_reader.iter(&admin_commands) {
+        let response = match &command.kind {
+            AdminCommandKind::Teleport { actor_id, position } => {
+                let player = query
+                    .iter_mut()
+                    .find(|(player_owner, _, _)| player_owner.0 == *actor_id);
+
+                match player {
+                    Some((_, mut target_position, mut transform)) => {
+                        target_position.position = *position;
+                        transform.translation = position.extend(transform.translation.z);
+
+                        format!("teleported {:?} to {:?}", actor_id, position)
+                    }
+                    None => format!("{:?} has no player", actor_id),
+                }
+            }
+            AdminCommandKind::Spawn { name, args } => {
+                admin_spawns.spawn(name, args, &mut *network_handle)
+            }
+            _ => continue,
+        };
+
+        admin_responses.send(AdminResponse::new(command.source, response));
+    }
+}
+
+fn spawn_tile(args: &[String], network_handle: &mut NetworkHandle) -> Option<String> {
+    let tile = match args {
+        [x, y, z] => Tile {
+            x: x.parse().ok()?,
+            y: y.parse().ok()?,
+            z: z.parse().ok()?,
+        },
+        _ => return None,
+    };
+
+    let response = format!("spawned tile at {} {} {}", tile.x, tile.y, tile.z);
+    network_handle.spawn(NetworkTarget::All, TileSpawnable { tile });
+
+    Some(response)
+}
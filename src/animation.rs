@@ -16,13 +16,12 @@ impl Animation {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, NetworkTypeUuid, SyncableComponent)]
 pub struct Animator {
     animations: HashMap<String, Animation>,
     current_animation: String,
     current_frame_time: f32,
 }
-serde_sync!(Animator = 768961378612578127962342341342);
 
 impl Animator {
     pub fn new() -> AnimatorBuilder {
@@ -70,7 +69,7 @@ impl AnimatorBuilder {
     }
 }
 
-pub fn animator_system(time: Res<Time>, mut query: Query<&mut Animator>) {
+pub fn animator_system(sim_time: Res<SimTime>, mut query: Query<&mut Animator>) {
     for mut animator in query.iter_mut() {
         let Animator {
             animations,
@@ -79,7 +78,7 @@ pub fn animator_system(time: Res<Time>, mut query: Query<&mut Animator>) {
         } = &mut *animator;
 
         let current_animation = &animations[current_animation];
-        *current_frame_time += time.delta_seconds();
+        *current_frame_time += sim_time.delta_seconds();
 
         if *current_frame_time
             > current_animation.frame_rate * (current_animation.textures.len() - 1) as f32
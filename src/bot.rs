@@ -0,0 +1,201 @@
+use crate::*;
+use rand::prelude::*;
+use std::{
+    sync::{mpsc::Sender, Mutex},
+    time::Duration,
+};
+
+pub struct BotInput {
+    timer: Timer,
+    rng: StdRng,
+}
+
+impl Default for BotInput {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(1.0, true),
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+/// Walks the player in a random direction, picking a new one every second.
+pub fn bot_input_system(
+    time: Res<Time>,
+    player: Res<Player>,
+    mut bot_input: Local<BotInput>,
+    mut query: Query<&mut MovementDirection>,
+) {
+    if !bot_input.timer.tick(time.delta_seconds()).just_finished() {
+        return;
+    }
+
+    if let Some(entity) = &player.entity {
+        if let Ok(mut movement_direction) = query.get_mut(*entity) {
+            let x = bot_input.rng.gen_range(-1, 2) as f32;
+            let y = bot_input.rng.gen_range(-1, 2) as f32;
+
+            movement_direction.direction = Vec2::new(x, y);
+        }
+    }
+}
+
+/// What a bot tells the runner while it runs, any but `Connected` fails the run.
+#[derive(Debug)]
+pub enum BotEvent {
+    Connected,
+    /// The connection closed before the bot was done.
+    Disconnected(String),
+    Failed(String),
+}
+
+/// Sends a bot's [`BotEvent`]s to the runner, tagged with the bot's index.
+pub struct BotEvents {
+    bot: usize,
+    sender: Mutex<Sender<(usize, BotEvent)>>,
+}
+
+impl BotEvents {
+    pub fn new(bot: usize, sender: Sender<(usize, BotEvent)>) -> Self {
+        Self {
+            bot,
+            sender: Mutex::new(sender),
+        }
+    }
+
+    pub fn send(&self, event: BotEvent) {
+        // the runner only stops listening once every bot is done
+        let _ = self.sender.lock().unwrap().send((self.bot, event));
+    }
+}
+
+pub fn bot_connection_system(
+    bot_events: Res<BotEvents>,
+    mut event_reader: Local<EventReader<ConnectionEvent>>,
+    events: Res<Events<ConnectionEvent>>,
+) {
+    for event in event_reader.iter(&events) {
+        if let ConnectionEvent::Disconnected { cause, .. } = event {
+            bot_events.send(BotEvent::Disconnected(format!("{:?}", cause)));
+        }
+    }
+}
+
+/// What a single bot saw of the server.
+pub struct BotReport {
+    pub connected: bool,
+    pub round_trip_time: Option<Duration>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub elapsed: Duration,
+}
+
+impl BotReport {
+    pub fn failed() -> Self {
+        Self {
+            connected: false,
+            round_trip_time: None,
+            bytes_sent: 0,
+            bytes_received: 0,
+            elapsed: Duration::default(),
+        }
+    }
+
+    pub fn new(connection_manager: &ConnectionManager, elapsed: Duration) -> Self {
+        let local_actor_id = connection_manager.get_local_actor().map(Actor::id);
+
+        let mut report = Self {
+            connected: false,
+            elapsed,
+            ..Self::failed()
+        };
+
+        for (_, connection) in connection_manager.connections() {
+            if Some(connection.actor().id()) == local_actor_id {
+                continue;
+            }
+
+            let statistics = connection.statistics();
+
+            report.connected = true;
+            report.round_trip_time = statistics.round_trip_time;
+            report.bytes_sent += statistics.bytes_sent;
+            report.bytes_received += statistics.bytes_received;
+        }
+
+        report
+    }
+
+    pub fn bytes_sent_per_second(&self) -> f64 {
+        self.bytes_sent as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn bytes_received_per_second(&self) -> f64 {
+        self.bytes_received as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Round trip time in network ticks of `tick`.
+    pub fn round_trip_ticks(&self, tick: Duration) -> Option<f64> {
+        self.round_trip_time
+            .map(|round_trip_time| round_trip_time.as_secs_f64() / tick.as_secs_f64())
+    }
+}
+
+pub fn print_bot_reports(reports: &[BotReport], tick: Duration) {
+    println!(
+        "{:>4} {:>9} {:>8} {:>8} {:>12} {:>12}",
+        "bot", "connected", "rtt ms", "rtt tick", "sent B/s", "recv B/s"
+    );
+
+    for (i, report) in reports.iter().enumerate() {
+        println!(
+            "{:>4} {:>9} {:>8} {:>8} {:>12.0} {:>12.0}",
+            i,
+            report.connected,
+            report
+                .round_trip_time
+                .map(|rtt| format!("{:.1}", rtt.as_secs_f64() * 1000.0))
+                .unwrap_or_else(|| "-".to_string()),
+            report
+                .round_trip_ticks(tick)
+                .map(|ticks| format!("{:.2}", ticks))
+                .unwrap_or_else(|| "-".to_string()),
+            report.bytes_sent_per_second(),
+            report.bytes_received_per_second(),
+        );
+    }
+
+    let connected: Vec<_> = reports.iter().filter(|report| report.connected).collect();
+    let round_trip_times: Vec<_> = connected
+        .iter()
+        .filter_map(|report| report.round_trip_time)
+        .collect();
+
+    println!("connected: {}/{}", connected.len(), reports.len());
+
+    if !round_trip_times.is_empty() {
+        let total: Duration = round_trip_times.iter().sum();
+
+        println!(
+            "mean rtt: {:.1} ms, max rtt: {:.1} ms",
+            (total / round_trip_times.len() as u32).as_secs_f64() * 1000.0,
+            round_trip_times.iter().max().unwrap().as_secs_f64() * 1000.0
+        );
+    }
+
+    if !connected.is_empty() {
+        println!(
+            "mean bandwidth per bot: {:.0} B/s sent, {:.0} B/s received",
+            connected
+                .iter()
+                .map(|report| report.bytes_sent_per_second())
+                .sum::<f64>()
+                / connected.len() as f64,
+            connected
+                .iter()
+                .map(|report| report.bytes_received_per_second())
+                .sum::<f64>()
+                / connected.len() as f64,
+        );
+    }
+}
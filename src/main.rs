@@ -2,16 +2,22 @@ use bevy::prelude::*;
 use clap::*;
 use std::net::{TcpListener, TcpStream};
 
+pub mod admin;
 pub mod animation;
+pub mod bot;
 pub mod component;
 pub mod map;
+pub mod network_log_overlay;
 pub mod player;
 pub mod target_position;
 pub mod z_sort;
+pub use admin::*;
 pub use animation::*;
+pub use bot::*;
 pub use component::*;
 pub use map::*;
 pub use network::*;
+pub use network_log_overlay::*;
 pub use player::*;
 pub use target_position::*;
 pub use z_sort::*;
@@ -20,57 +26,275 @@ pub use z_sort::*;
 enum Mode {
     Server(Server),
     Client(Client),
+    Replay(Playback),
+    Bot(Bots),
+    Admin(AdminClient),
+}
+
+/// Simulated network conditions, delays are in network ticks.
+#[derive(Clap)]
+struct Conditions {
+    #[clap(long, default_value = "0")]
+    latency: u32,
+    #[clap(long, default_value = "0")]
+    jitter: u32,
+    #[clap(long, default_value = "0", parse(try_from_str = chance))]
+    loss: f32,
+    #[clap(long, default_value = "0", parse(try_from_str = chance))]
+    duplication: f32,
+    #[clap(long, default_value = "0", parse(try_from_str = chance))]
+    reordering: f32,
+    #[clap(long, default_value = "0")]
+    seed: u64,
+}
+
+fn chance(chance: &str) -> std::result::Result<f32, String> {
+    match chance.parse::<f32>() {
+        Ok(chance) if (0.0..=1.0).contains(&chance) => Ok(chance),
+        Ok(_) => Err("must be between 0 and 1".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl Conditions {
+    pub fn link_conditioner(&self) -> Option<network::LinkConditioner> {
+        let link_conditioner = network::LinkConditioner {
+            latency_ticks: self.latency,
+            jitter_ticks: self.jitter,
+            loss: self.loss,
+            duplication: self.duplication,
+            reordering: self.reordering,
+            seed: self.seed,
+        };
+
+        if self.latency > 0
+            || self.jitter > 0
+            || self.loss > 0.0
+            || self.duplication > 0.0
+            || self.reordering > 0.0
+        {
+            Some(link_conditioner)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Clap)]
 struct Server {
     ip: std::net::SocketAddr,
+    /// File to store player accounts in, logging in is required when set.
+    #[clap(long)]
+    accounts: Option<std::path::PathBuf>,
+    /// Creates an account for unknown usernames the first time they log in, requires --accounts.
+    #[clap(long, requires = "accounts")]
+    register: bool,
+    #[clap(flatten)]
+    conditions: Conditions,
+    /// Records the session to this file.
+    #[clap(long)]
+    record: Option<std::path::PathBuf>,
+    /// cbor or bincode, clients use whatever the server picks.
+    #[clap(long, default_value = "cbor")]
+    wire_format: network::WireFormat,
+    /// Usernames allowed to connect with the admin subcommand, requires --accounts and an
+    /// existing account for each.
+    #[clap(long, requires = "accounts")]
+    admin: Vec<String>,
+    /// Only lets these addresses or usernames in.
+    #[clap(long)]
+    allow: Vec<String>,
+    /// Connections a single address can open per minute.
+    #[clap(long)]
+    rate_limit: Option<usize>,
+    #[clap(long)]
+    max_players: Option<usize>,
+    /// Network and simulation ticks per second.
+    #[clap(long, default_value = "20")]
+    tick_rate: std::num::NonZeroU32,
+    /// File with the server's identity key, created if it doesn't exist. Clients pin its public
+    /// key, printed on startup, with --server-key.
+    #[clap(long)]
+    identity: Option<std::path::PathBuf>,
+    /// Splits the world up between servers, like `1:0,-500,1000,500@127.0.0.1:9001`. Every zone
+    /// server needs the same --identity.
+    #[clap(long, requires = "identity")]
+    zone: Vec<network::Zone>,
+    /// The zone this server simulates, requires --zone.
+    #[clap(long)]
+    zone_id: Option<u32>,
+    /// Shared by every zone server, requires --zone.
+    #[clap(long)]
+    zone_secret: Option<String>,
 }
 
 impl Server {
     pub fn run(&self) {
         let listener = TcpListener::bind(self.ip).unwrap();
 
-        bevy::prelude::App::build()
+        let mut settings = network::NetworkSettings::server();
+        settings.tick_duration = tick_duration(self.tick_rate);
+        settings.link_conditioner = self.conditions.link_conditioner();
+        settings.record = self.record.clone();
+        settings.wire_format = self.wire_format;
+
+        let identity = match &self.identity {
+            Some(path) => network::Identity::open(path).expect("Failed to open identity"),
+            None => network::Identity::generate(),
+        };
+        println!("Identity key: {}", identity.public_key());
+        settings.identity = Some(identity);
+
+        let mut admin_plugin = network::AdminPlugin::default();
+
+        for username in &self.admin {
+            admin_plugin = admin_plugin.with_admin(username);
+        }
+
+        let mut account_store = network::AccountStore::new();
+        // nothing to take over without a file, names are only kept until the server stops
+        account_store.register_unknown = true;
+
+        if let Some(path) = &self.accounts {
+            account_store = network::AccountStore::open(path).expect("Failed to open accounts");
+            account_store.register_unknown = self.register;
+            settings.require_login = true;
+        }
+
+        for username in &self.admin {
+            if account_store.get_player_id(username).is_none() {
+                eprintln!(
+                    "Admin {} has no account yet, log in with it once on a server started with \
+                     --register before making it an admin",
+                    username
+                );
+                std::process::exit(1);
+            }
+        }
+
+        let mut access_control = network::AccessControl::new();
+        access_control.max_players = self.max_players;
+        access_control.rate_limit = self.rate_limit.map(|connections| network::RateLimit {
+            connections,
+            window: std::time::Duration::from_secs(60),
+        });
+
+        if !self.allow.is_empty() {
+            let mut allowlist = network::Allowlist::default();
+
+            for allowed in &self.allow {
+                if let Ok(addr) = allowed.parse() {
+                    allowlist.addrs.insert(addr);
+                } else if let Some(player_id) = account_store.get_player_id(allowed) {
+                    allowlist.players.insert(player_id);
+                } else {
+                    eprintln!("{} is neither an address nor a known username", allowed);
+                    std::process::exit(1);
+                }
+            }
+
+            access_control.allowlist = Some(allowlist);
+        }
+
+        let mut app_builder = bevy::prelude::App::build();
+
+        app_builder
             // resources
             .init_resource::<Map>()
+            .add_resource(account_store)
+            .add_resource(access_control)
+            .init_resource::<AdminSpawns>()
             .add_resource(bevy::app::ScheduleRunnerSettings::run_loop(
-                std::time::Duration::from_secs_f32(1.0 / 20.0),
+                settings.tick_duration,
             ))
             // plugins
-            .add_plugin(network::NetworkPlugin::server(listener))
+            .add_plugin(network::NetworkPlugin::server(listener).with_settings(settings))
+            .add_plugin(
+                network::NetworkDiagnosticsPlugin::default()
+                    .with_log_interval(std::time::Duration::from_secs(30)),
+            )
+            .add_plugin(admin_plugin)
             .add_plugins(MinimalPlugins)
-            .add_plugin(bevy::log::LogPlugin)
-            // component sync
-            .add_component_sync::<MovementDirection>()
-            .add_component_sync::<Transform>()
-            .add_component_sync::<TargetPosition>()
-            .add_component_sync::<Tile>()
-            .add_component_sync::<Animator>()
+            .add_plugin(bevy::log::LogPlugin);
+
+        add_game_sync(&mut app_builder)
             // startup systems
             .add_startup_system(setup_server)
             // systems
             .add_system(server_connection_handler)
             .add_system(player_spawn_system)
-            .add_system(player_movement_system)
-            .add_system(target_position_system)
             .add_system(tile_transform_system)
-            .add_system(animator_system)
-            // run
-            .run();
+            .add_system(admin_command_system)
+            // simulation systems
+            .add_system_to_stage(network::stage::SIMULATION, player_movement_system)
+            .add_system_to_stage(network::stage::SIMULATION, target_position_system)
+            .add_system_to_stage(network::stage::SIMULATION, animator_system);
+
+        if !self.zone.is_empty() {
+            let zone_id = self.zone_id.expect("--zone requires --zone-id");
+            let secret = self
+                .zone_secret
+                .clone()
+                .expect("--zone requires --zone-secret");
+
+            app_builder.add_plugin(network::ZonePlugin {
+                settings: network::ZoneSettings::new(
+                    self.zone.clone(),
+                    network::ZoneId(zone_id),
+                    secret,
+                ),
+            });
+        }
+
+        app_builder.run();
     }
 }
 
 #[derive(Clap)]
 struct Client {
     ip: String,
+    /// Logs in as this user, the password is read from MMO_PASSWORD or stdin.
+    #[clap(long)]
+    username: Option<String>,
+    /// Only connects to servers proving they hold this identity key.
+    #[clap(long)]
+    server_key: Option<network::IdentityKey>,
+    #[clap(flatten)]
+    conditions: Conditions,
+    /// Records the session to this file.
+    #[clap(long)]
+    record: Option<std::path::PathBuf>,
+    /// Network and simulation ticks per second, has to match the server's.
+    #[clap(long, default_value = "20")]
+    tick_rate: std::num::NonZeroU32,
+    /// Shows recent payloads on top of the game, toggled with F3.
+    #[clap(long)]
+    net_log: bool,
+    /// Only shows payloads whose type name contains this, requires --net-log.
+    #[clap(long, requires = "net-log")]
+    net_log_filter: Option<String>,
 }
 
 impl Client {
     pub fn run(&self) {
-        let stream = TcpStream::connect(self.ip.clone()).unwrap();
+        let mut settings = network::NetworkSettings::client();
+        settings.tick_duration = tick_duration(self.tick_rate);
+        settings.link_conditioner = self.conditions.link_conditioner();
+        settings.record = self.record.clone();
+        settings.server_key = self.server_key;
+
+        if let Some(username) = &self.username {
+            settings.credentials =
+                Some(network::Credentials::new(username, read_password(username)));
+        }
 
-        bevy::prelude::App::build()
+        if self.net_log {
+            settings.log = Some(500);
+        }
+
+        let mut app_builder = bevy::prelude::App::build();
+
+        app_builder
             // resources
             .init_resource::<player::Player>()
             .init_resource::<Map>()
@@ -79,29 +303,299 @@ impl Client {
                 ..Default::default()
             })
             // plugins
-            .add_plugin(network::NetworkPlugin::client(stream))
-            .add_plugins(DefaultPlugins)
-            // component sync
-            .add_component_sync::<MovementDirection>()
-            .add_component_sync::<Transform>()
-            .add_component_sync::<TargetPosition>()
-            .add_component_sync::<Tile>()
-            .add_component_sync::<Animator>()
+            .add_plugin(network::NetworkPlugin::connect(&self.ip).with_settings(settings))
+            .add_plugins(DefaultPlugins);
+
+        add_game_sync(&mut app_builder)
             // startup systems
             .add_startup_system(setup_client)
             // systems
+            .add_system(client_connection_handler)
             .add_system(player_input_system)
             .add_system(player_camera_system)
             .add_system(target_position_system)
             .add_system(tile_transform_system)
-            .add_system(animator_system)
             .add_system(animator_sprite_system)
             .add_system(z_sort_system)
+            // simulation systems
+            .add_system_to_stage(network::stage::SIMULATION, animator_system);
+
+        if self.net_log {
+            app_builder
+                .add_resource(NetworkLogOverlay {
+                    visible: true,
+                    filter: self.net_log_filter.clone(),
+                })
+                .add_startup_system(setup_network_log_overlay)
+                .add_system(network_log_overlay_system);
+        }
+
+        app_builder.run();
+    }
+}
+
+/// Watches a recorded client session, space pauses and right arrow steps while paused.
+#[derive(Clap)]
+struct Playback {
+    path: std::path::PathBuf,
+}
+
+impl Playback {
+    pub fn run(&self) {
+        let mut app_builder = bevy::prelude::App::build();
+
+        app_builder
+            // resources
+            .init_resource::<player::Player>()
+            .init_resource::<Map>()
+            .add_resource(WindowDescriptor {
+                vsync: true,
+                ..Default::default()
+            })
+            // plugins
+            .add_plugin(network::NetworkPlugin::replay(&self.path))
+            .add_plugins(DefaultPlugins);
+
+        add_game_sync(&mut app_builder)
+            // startup systems
+            .add_startup_system(setup_client)
+            // systems
+            .add_system(replay_control_system)
+            .add_system(player_camera_system)
+            .add_system(target_position_system)
+            .add_system(tile_transform_system)
+            .add_system(animator_sprite_system)
+            .add_system(z_sort_system)
+            // simulation systems
+            .add_system_to_stage(network::stage::SIMULATION, animator_system)
             // run
             .run();
     }
 }
 
+/// Headless clients walking around at random, prints what each saw when done.
+#[derive(Clap)]
+struct Bots {
+    ip: String,
+    #[clap(long, default_value = "10")]
+    count: usize,
+    /// Seconds to run for.
+    #[clap(long, default_value = "60")]
+    duration: u64,
+    /// Milliseconds between bots connecting.
+    #[clap(long, default_value = "50")]
+    ramp_up: u64,
+    /// Network and simulation ticks per second, has to match the server's.
+    #[clap(long, default_value = "20")]
+    tick_rate: std::num::NonZeroU32,
+    /// Only connects to servers proving they hold this identity key.
+    #[clap(long)]
+    server_key: Option<network::IdentityKey>,
+}
+
+impl Bots {
+    /// Exits with an error if any bot failed to connect, disconnected early or panicked.
+    pub fn run(&self) {
+        let duration = std::time::Duration::from_secs(self.duration);
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let handles: Vec<_> = (0..self.count)
+            .map(|i| {
+                std::thread::sleep(std::time::Duration::from_millis(self.ramp_up));
+
+                let ip = self.ip.clone();
+                let tick_duration = tick_duration(self.tick_rate);
+                let server_key = self.server_key;
+                let bot_events = BotEvents::new(i, sender.clone());
+
+                std::thread::spawn(move || {
+                    run_bot(&ip, duration, tick_duration, server_key, bot_events)
+                })
+            })
+            .collect();
+
+        drop(sender);
+
+        let mut failed = std::collections::HashSet::new();
+
+        for (i, event) in receiver {
+            match event {
+                BotEvent::Connected => (),
+                BotEvent::Disconnected(_) | BotEvent::Failed(_) => {
+                    println!("Bot {}: {:?}", i, event);
+                    failed.insert(i);
+                }
+            }
+        }
+
+        let reports: Vec<_> = handles
+            .into_iter()
+            .enumerate()
+            .map(|(i, handle)| {
+                handle.join().unwrap_or_else(|_| {
+                    println!("Bot {} panicked", i);
+                    failed.insert(i);
+                    BotReport::failed()
+                })
+            })
+            .collect();
+
+        print_bot_reports(&reports, tick_duration(self.tick_rate));
+
+        if !failed.is_empty() {
+            eprintln!("{} of {} bots didn't run cleanly", failed.len(), self.count);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_bot(
+    ip: &str,
+    duration: std::time::Duration,
+    tick_duration: std::time::Duration,
+    server_key: Option<network::IdentityKey>,
+    bot_events: BotEvents,
+) -> BotReport {
+    let mut settings = network::NetworkSettings::client();
+    settings.tick_duration = tick_duration;
+    settings.server_key = server_key;
+
+    let mut connection_manager = network::ConnectionManager::new(settings.actor_ty);
+
+    let connected = TcpStream::connect(ip)
+        .map_err(network::Error::from)
+        .and_then(|stream| {
+            connection_manager.add_connection(
+                stream,
+                settings.connection_ty,
+                network::Handshake::new(network::HandshakeActorIds::None, &settings),
+                &settings,
+                None,
+            )
+        });
+
+    match connected {
+        Ok(_) => bot_events.send(BotEvent::Connected),
+        Err(e) => {
+            bot_events.send(BotEvent::Failed(format!("{:?}", e)));
+            return BotReport::failed();
+        }
+    }
+
+    let mut app_builder = bevy::prelude::App::build();
+
+    app_builder
+        // resources
+        .init_resource::<player::Player>()
+        .init_resource::<Map>()
+        .add_resource(bot_events)
+        // bots already get a thread each, the default pools would take every core per bot
+        .add_resource(bevy::core::DefaultTaskPoolOptions::with_num_threads(1))
+        // plugins
+        .add_plugin(network::NetworkPlugin::connected(connection_manager).with_settings(settings))
+        .add_plugin(network::NetworkDiagnosticsPlugin::default())
+        .add_plugins(MinimalPlugins);
+
+    add_game_sync(&mut app_builder)
+        // systems
+        .add_system(bot_input_system)
+        .add_system(bot_connection_system)
+        .add_system(target_position_system)
+        .add_system(tile_transform_system)
+        // simulation systems
+        .add_system_to_stage(network::stage::SIMULATION, animator_system);
+
+    let mut app = app_builder.app;
+    let start = std::time::Instant::now();
+
+    while start.elapsed() < duration {
+        app.update();
+        std::thread::sleep(std::time::Duration::from_secs_f32(1.0 / 60.0));
+    }
+
+    let connection_manager = app.resources.get::<network::ConnectionManager>().unwrap();
+
+    BotReport::new(&*connection_manager, start.elapsed())
+}
+
+/// Sends lines typed into stdin to the server as admin commands.
+#[derive(Clap)]
+struct AdminClient {
+    ip: String,
+    /// The password is read from MMO_PASSWORD or the first line of stdin.
+    #[clap(long)]
+    username: String,
+    /// Only connects to servers proving they hold this identity key.
+    #[clap(long)]
+    server_key: Option<network::IdentityKey>,
+    /// Network and simulation ticks per second, has to match the server's.
+    #[clap(long, default_value = "20")]
+    tick_rate: std::num::NonZeroU32,
+}
+
+impl AdminClient {
+    pub fn run(&self) {
+        let mut settings = network::NetworkSettings::client();
+        settings.credentials = Some(network::Credentials::new(
+            &self.username,
+            read_password(&self.username),
+        ));
+        settings.tick_duration = tick_duration(self.tick_rate);
+        settings.server_key = self.server_key;
+
+        let mut app_builder = bevy::prelude::App::build();
+
+        app_builder
+            // resources
+            .init_resource::<player::Player>()
+            .init_resource::<Map>()
+            .add_resource(network::AdminConsole::stdin())
+            .add_resource(bevy::app::ScheduleRunnerSettings::run_loop(
+                settings.tick_duration,
+            ))
+            // plugins
+            .add_plugin(network::NetworkPlugin::connect(&self.ip).with_settings(settings))
+            .add_plugins(MinimalPlugins)
+            .add_plugin(bevy::log::LogPlugin);
+
+        add_game_sync(&mut app_builder)
+            // systems
+            .add_system(admin_client_system)
+            // run
+            .run();
+    }
+}
+
+/// Syncs the components every mode shares, after the network plugin.
+fn add_game_sync(app_builder: &mut AppBuilder) -> &mut AppBuilder {
+    app_builder
+        .add_component_sync::<MovementDirection>()
+        .add_compact_transform_sync(CompactTransformSettings::default())
+        .add_component_sync::<TargetPosition>()
+        .add_component_sync::<Tile>()
+        .add_component_sync::<Animator>()
+}
+
+/// Passwords stay out of the arguments, anyone on the machine can list those.
+fn read_password(username: &str) -> String {
+    if let Ok(password) = std::env::var("MMO_PASSWORD") {
+        return password;
+    }
+
+    eprint!("Password for {}: ", username);
+
+    let mut password = String::new();
+    std::io::stdin()
+        .read_line(&mut password)
+        .expect("Failed to read the password");
+
+    password.trim_end_matches(&['\r', '\n'][..]).to_string()
+}
+
+fn tick_duration(tick_rate: std::num::NonZeroU32) -> std::time::Duration {
+    std::time::Duration::from_secs_f64(1.0 / tick_rate.get() as f64)
+}
+
 #[derive(Clap)]
 #[clap(version = crate_version!(), author = "Hjalte Nannestad")]
 struct Options {
@@ -115,6 +609,35 @@ fn main() {
     match options.mode {
         Mode::Server(server) => server.run(),
         Mode::Client(client) => client.run(),
+        Mode::Replay(playback) => playback.run(),
+        Mode::Bot(bots) => bots.run(),
+        Mode::Admin(admin_client) => admin_client.run(),
+    }
+}
+
+fn replay_control_system(input: Res<Input<KeyCode>>, mut replay: ResMut<network::Replay>) {
+    if input.just_pressed(KeyCode::Space) {
+        if replay.is_paused() {
+            replay.resume();
+        } else {
+            replay.pause();
+        }
+    }
+
+    if input.just_pressed(KeyCode::Right) && replay.is_paused() {
+        replay.step();
+    }
+}
+
+fn admin_client_system(
+    console: Res<network::AdminConsole>,
+    mut network_handle: ResMut<NetworkHandle>,
+) {
+    for line in console.lines() {
+        network_handle.add_payload(
+            NetworkTarget::ActorTy(ActorTy::new::<network::Server>()),
+            Payload::Admin(AdminPayload::Command(line)),
+        );
     }
 }
 
@@ -127,11 +650,15 @@ fn server_connection_handler(
             ConnectionEvent::Connected {
                 actor,
                 connection_id,
+                player_id,
+                resumed,
             } => {
                 println!(
-                    "Player Connected: {:?}, given actor id: {:?}",
+                    "Player Connected: {:?}, given actor id: {:?}, player id: {:?}, resumed: {}",
                     connection_id,
-                    actor.id()
+                    actor.id(),
+                    player_id,
+                    resumed
                 );
             }
             ConnectionEvent::Disconnected {
@@ -150,6 +677,17 @@ fn server_connection_handler(
     }
 }
 
+fn client_connection_handler(
+    mut event_reader: Local<EventReader<ConnectionEvent>>,
+    events: Res<Events<ConnectionEvent>>,
+) {
+    for event in event_reader.iter(&events) {
+        if let ConnectionEvent::Disconnected { cause, .. } = event {
+            println!("Disconnected from server: {:?}", cause);
+        }
+    }
+}
+
 fn setup_server(commands: &mut Commands) {
     commands.spawn(Camera2dBundle {
         ..Default::default()
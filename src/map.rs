@@ -1,7 +1,7 @@
 use crate::*;
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, NetworkTypeUuid, SyncableComponent)]
 pub struct Tile {
     pub x: i32,
     pub y: i32,
@@ -18,18 +18,15 @@ impl Tile {
     }
 }
 
-serde_sync!(Tile = 864817564917835867578123423412123);
-
 #[derive(Default)]
 pub struct Map {
     pub tiles: HashMap<Tile, Entity>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, NetworkTypeUuid)]
 pub struct TileSpawnable {
-    tile: Tile,
+    pub tile: Tile,
 }
-network_uuid!(TileSpawnable = 56234786109236180564623789623789);
 
 #[typetag::serde]
 impl Spawnable for TileSpawnable {
@@ -47,7 +44,7 @@ impl Spawnable for TileSpawnable {
             .with(Transform::default())
             .with(GlobalTransform::default())
             .with(self.tile.clone())
-            .with(ComponentSync::<Tile>::ty(ActorTy::new::<Server>()))
+            .with(ComponentSync::<Tile>::ty(ActorTy::new::<network::Server>()))
             .current_entity()
             .unwrap();
 
@@ -0,0 +1,101 @@
+use crate::*;
+
+/// Lines of the network log shown at once.
+const LINES: usize = 24;
+
+/// Lists recent payloads from the [`NetworkLog`] on top of the game, toggled with F3.
+pub struct NetworkLogOverlay {
+    pub visible: bool,
+    /// Only shows payloads whose type name contains this.
+    pub filter: Option<String>,
+}
+
+pub struct NetworkLogText;
+
+pub fn setup_network_log_overlay(commands: &mut Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(CameraUiBundle::default())
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+                style: TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(NetworkLogText);
+}
+
+pub fn network_log_overlay_system(
+    input: Res<Input<KeyCode>>,
+    mut overlay: ResMut<NetworkLogOverlay>,
+    network_log: Res<NetworkLog>,
+    network_type_registry: Res<NetworkTypeRegistry>,
+    mut query: Query<&mut Text, With<NetworkLogText>>,
+) {
+    if input.just_pressed(KeyCode::F3) {
+        overlay.visible = !overlay.visible;
+    }
+
+    let mut lines = Vec::new();
+
+    if overlay.visible {
+        match &overlay.filter {
+            Some(filter) => lines.push(format!("network log, showing {}", filter)),
+            None => lines.push("network log".to_string()),
+        }
+
+        for entry in network_log
+            .entries()
+            .rev()
+            .filter(|entry| match &overlay.filter {
+                Some(filter) => entry.matches(filter, &*network_type_registry),
+                None => true,
+            })
+            .take(LINES)
+        {
+            let peer = match (&entry.sender, &entry.target) {
+                (Some(sender), _) => format!("from {:?}", sender.id()),
+                (_, Some(target)) => format!("to {:?}", target),
+                _ => String::new(),
+            };
+
+            lines.push(format!(
+                "{:>6} {} {:<24} {:<18} {:>6}B {}",
+                entry.tick.0,
+                match entry.direction {
+                    LogDirection::Incoming => "<-",
+                    LogDirection::Outgoing => "->",
+                },
+                entry
+                    .type_name(&*network_type_registry)
+                    .rsplit("::")
+                    .next()
+                    .unwrap_or_default(),
+                entry
+                    .network_entity
+                    .map(|network_entity| format!("{:?}", network_entity))
+                    .unwrap_or_default(),
+                entry.size,
+                peer,
+            ));
+        }
+    }
+
+    for mut text in query.iter_mut() {
+        text.value = lines.join("\n");
+    }
+}
@@ -1,15 +1,16 @@
 use crate::*;
 use network::*;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, NetworkTypeUuid, SyncableComponent)]
 pub struct MovementDirection {
-    direction: Vec2,
+    pub direction: Vec2,
 }
-serde_sync!(MovementDirection = 2658768195371452387452783612347);
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, NetworkTypeUuid, SyncableComponent)]
 pub struct MovementSpeed(pub f32);
-serde_sync!(MovementSpeed = 46197851341234126834145234623594234);
+
+/// The actor controlling a player entity.
+pub struct PlayerOwner(pub ActorId);
 
 #[derive(Serialize, Deserialize)]
 pub struct PlayerSpawnable {
@@ -52,28 +53,38 @@ impl Spawnable for PlayerSpawnable {
             .with(animator.build())
             .with(ComponentSync::<MovementDirection>::id(self.actor_id))
             .with(ComponentSync::<TargetPosition>::id(ctx.sender_id()))
-            .with(ComponentSync::<Animator>::ty(ActorTy::new::<Server>()))
+            .with(ComponentSync::<Animator>::ty(ActorTy::new::<network::Server>()))
+            .with(PlayerOwner(self.actor_id))
             .current_entity()
             .unwrap();
 
-        if ctx.local_ty().is::<Client>() {
-            let asset_server = resources.get::<AssetServer>().unwrap();
-            let mut texture_atlases = resources.get_mut::<Assets<TextureAtlas>>().unwrap();
-            let mut player = resources.get_mut::<Player>().unwrap();
+        if ctx.local_ty().is::<network::Server>() {
+            commands.with(ZoneEntity {
+                owner: Some(self.actor_id),
+            });
+        }
 
-            let texture_handle = asset_server.load("sheet.png");
-            let texture_atlas = texture_atlases.add(TextureAtlas::from_grid(
-                texture_handle,
-                Vec2::new(128.0, 128.0),
-                24*8,
-                1,
-            ));
+        if ctx.local_ty().is::<network::Client>() {
+            let mut player = resources.get_mut::<Player>().unwrap();
 
-            commands
-                .with_bundle(SpriteSheetBundle {
-                    texture_atlas,
-                    ..Default::default()
-                });
+            // bots don't render
+            if let Some(asset_server) = resources.get::<AssetServer>() {
+                let mut texture_atlases = resources.get_mut::<Assets<TextureAtlas>>().unwrap();
+
+                let texture_handle = asset_server.load("sheet.png");
+                let texture_atlas = texture_atlases.add(TextureAtlas::from_grid(
+                    texture_handle,
+                    Vec2::new(128.0, 128.0),
+                    24*8,
+                    1,
+                ));
+
+                commands
+                    .with_bundle(SpriteSheetBundle {
+                        texture_atlas,
+                        ..Default::default()
+                    });
+            }
 
             if ctx.local_id() == self.actor_id {
                 player.entity = Some(entity);
@@ -82,6 +93,10 @@ impl Spawnable for PlayerSpawnable {
 
         entity
     }
+
+    fn set_owner(&mut self, actor_id: ActorId) {
+        self.actor_id = actor_id;
+    }
 }
 
 pub fn player_input_system(
@@ -144,7 +159,7 @@ const MOVEMENT_FRAMES: [bool; 24] = [
 ];
 
 pub fn player_movement_system(
-    time: Res<Time>,
+    sim_time: Res<SimTime>,
     mut query: Query<(
         &MovementDirection,
         &MovementSpeed,
@@ -158,7 +173,7 @@ pub fn player_movement_system(
             let direction = movement_direction.direction.normalize();
 
             if MOVEMENT_FRAMES[animator.current_frame()] {
-                target_position.position += direction * movement_speed.0 * time.delta_seconds();
+                target_position.position += direction * movement_speed.0 * sim_time.delta_seconds();
             }
 
             let angle = direction.y.atan2(direction.x);
@@ -191,7 +206,12 @@ pub fn player_spawn_system(
     events: Res<Events<ConnectionEvent>>,
 ) {
     for event in event_reader.iter(&events) {
-        if let ConnectionEvent::Connected { actor, .. } = event {
+        if let ConnectionEvent::Connected { actor, resumed, .. } = event {
+            // a resumed actor still has its player, other zone servers don't get one
+            if *resumed || actor.ty().is::<ZoneServer>() {
+                continue;
+            }
+
             let player = PlayerSpawnable {
                 actor_id: actor.id(),
             };
@@ -1,13 +1,11 @@
 use crate::*;
 use serde::*;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, NetworkTypeUuid, SyncableComponent)]
 pub struct TargetPosition {
     pub position: Vec2,
 }
 
-serde_sync!(TargetPosition = 3461874691536481231254314);
-
 impl TargetPosition {
     pub fn new(position: Vec2) -> Self {
         Self { position }